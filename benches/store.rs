@@ -0,0 +1,211 @@
+// sentiric-sip-gateway-service/benches/store.rs
+//
+// İşlem ve diyalog depolarının eşzamanlı çağrılar altındaki verimini ölçer: parçalamadan
+// önceki düzen (her depo tek bir Mutex arkasında) ile Call-ID'ye göre parçalanmış depolar
+// karşılaştırılır. Her iki düzende de paket görevleri, handler'daki kilit sırasıyla
+// (işlem kilidi tutulurken diyalog kilidi) çalışır; zamanlayıcı görevleri de aynı anda
+// kilitleri alır.
+//
+// Çalıştırma: cargo bench --bench store
+use sentiric_sip_gateway_service::config::AppConfig;
+use sentiric_sip_gateway_service::sip::dialog::DialogStore;
+use sentiric_sip_gateway_service::sip::message::SipMessage;
+use sentiric_sip_gateway_service::sip::shard::{Sharded, DEFAULT_SHARD_COUNT};
+use sentiric_sip_gateway_service::sip::transaction::{client_key_for_response, Direction, TransactionInfo, TransactionKey, TransactionStore};
+use sentiric_sip_gateway_service::transport::Peer;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Her ölçümde işlenen toplam çağrı; görevlere eşit bölünür.
+const TOTAL_CALLS: usize = 102_400;
+const TASK_COUNTS: [usize; 3] = [16, 64, 256];
+/// Her düzen bu kadar kez ölçülür, en iyi sonuç raporlanır.
+const ROUNDS: usize = 3;
+/// Üretimdeki zamanlayıcı görevlerinin adımları.
+const TRANSACTION_TICK: Duration = Duration::from_millis(50);
+const DIALOG_TICK: Duration = Duration::from_secs(1);
+
+fn invite(call_id: &str, branch: &str) -> SipMessage {
+    let packet = format!(
+        "INVITE sip:1000@10.0.0.1 SIP/2.0\r\n\
+         Via: SIP/2.0/UDP 192.0.2.10:5060;branch={branch}\r\n\
+         Max-Forwards: 70\r\n\
+         From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+         To: <sip:1000@10.0.0.1>\r\n\
+         Call-ID: {call_id}\r\n\
+         CSeq: 1 INVITE\r\n\
+         Contact: <sip:2000@192.0.2.10:5060>\r\n\
+         Content-Length: 0\r\n\r\n"
+    );
+    SipMessage::parse(packet.as_bytes()).expect("geçerli INVITE")
+}
+
+fn ringing(call_id: &str, branch: &str) -> SipMessage {
+    let packet = format!(
+        "SIP/2.0 180 Ringing\r\n\
+         Via: SIP/2.0/UDP 10.0.0.2:5060;branch={branch}\r\n\
+         From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+         To: <sip:1000@10.0.0.1>;tag=b1\r\n\
+         Call-ID: {call_id}\r\n\
+         CSeq: 1 INVITE\r\n\
+         Content-Length: 0\r\n\r\n"
+    );
+    SipMessage::parse(packet.as_bytes()).expect("geçerli yanıt")
+}
+
+/// Bir görevin işleyeceği çağrılar: INVITE, işlem anahtarı, işlem kaydı ve gelen 180 yanıtı.
+/// Mesajlar ölçümden önce hazırlanır; böylece yalnızca depo işlemleri ölçülür.
+type Call = (SipMessage, TransactionKey, TransactionInfo, SipMessage);
+
+fn prepare(task: usize, calls: usize) -> Vec<Call> {
+    let remote: SocketAddr = "192.0.2.10:5060".parse().unwrap();
+    let target = Peer::internal("10.0.0.1:5060".parse().unwrap());
+    (0..calls)
+        .map(|call| {
+            let call_id = format!("bench-{task}-{call}");
+            let request = invite(&call_id, &format!("z9hG4bK.in{task}x{call}"));
+            let client_branch = format!("z9hG4bK.out{task}x{call}");
+            let response = ringing(&call_id, &client_branch);
+            let key = TransactionKey::for_request(&request).unwrap();
+            let info = TransactionInfo::new(Direction::Inbound, &request, Peer::udp(remote), Vec::new(), client_branch, target);
+            (request, key, info, response)
+        })
+        .collect()
+}
+
+/// Karşılaştırılan depo düzenleri.
+enum Stores {
+    /// Parçalamadan önceki düzen: her depo tek bir Mutex arkasında.
+    Global { transactions: Arc<Mutex<TransactionStore>>, dialogs: Arc<Mutex<DialogStore>> },
+    Sharded { transactions: Sharded<TransactionStore>, dialogs: Sharded<DialogStore> },
+}
+
+impl Stores {
+    fn name(&self) -> &'static str {
+        match self {
+            Stores::Global { .. } => "tek Mutex",
+            Stores::Sharded { .. } => "64 parça",
+        }
+    }
+
+    async fn transactions(&self, call_id: &str) -> MutexGuard<'_, TransactionStore> {
+        match self {
+            Stores::Global { transactions, .. } => transactions.lock().await,
+            Stores::Sharded { transactions, .. } => transactions.lock(call_id).await,
+        }
+    }
+
+    async fn dialogs(&self, call_id: &str) -> MutexGuard<'_, DialogStore> {
+        match self {
+            Stores::Global { dialogs, .. } => dialogs.lock().await,
+            Stores::Sharded { dialogs, .. } => dialogs.lock(call_id).await,
+        }
+    }
+
+    fn transaction_shards(&self) -> Vec<&Mutex<TransactionStore>> {
+        match self {
+            Stores::Global { transactions, .. } => vec![transactions.as_ref()],
+            Stores::Sharded { transactions, .. } => transactions.shards().collect(),
+        }
+    }
+
+    fn dialog_shards(&self) -> Vec<&Mutex<DialogStore>> {
+        match self {
+            Stores::Global { dialogs, .. } => vec![dialogs.as_ref()],
+            Stores::Sharded { dialogs, .. } => dialogs.shards().collect(),
+        }
+    }
+}
+
+/// `run_timers` ve `run_expiry` gibi zamanlayıcı çarklarını süreli olarak ilerletir.
+async fn run_timers(stores: Arc<Stores>, config: Arc<AppConfig>) {
+    let mut transaction_tick = tokio::time::interval(TRANSACTION_TICK);
+    let mut dialog_tick = tokio::time::interval(DIALOG_TICK);
+    loop {
+        tokio::select! {
+            _ = transaction_tick.tick() => {
+                for shard in stores.transaction_shards() {
+                    shard.lock().await.process_timers(Instant::now(), &config);
+                }
+            }
+            _ = dialog_tick.tick() => {
+                for shard in stores.dialog_shards() {
+                    shard.lock().await.process_timers(Instant::now());
+                }
+            }
+        }
+    }
+}
+
+/// Her görev, handler'daki kilit düzenini taklit eder: INVITE için işlem açılır, ardından
+/// gelen 180 yanıtı istemci anahtarıyla eşleştirilir; işlem kilidi tutulurken diyalog
+/// katmanına uygulanır ve yanıt sunucu işlemine kaydedilir.
+async fn run(stores: Arc<Stores>, tasks: usize, config: Arc<AppConfig>) -> Duration {
+    let workloads: Vec<Vec<Call>> = (0..tasks).map(|task| prepare(task, TOTAL_CALLS / tasks)).collect();
+    let timers = tokio::spawn(run_timers(stores.clone(), config));
+    let started = Instant::now();
+    let handles: Vec<_> = workloads
+        .into_iter()
+        .map(|calls| {
+            let stores = stores.clone();
+            tokio::spawn(async move {
+                for (request, key, info, response) in calls {
+                    stores.transactions(request.call_id()).await.insert(key, info);
+                    tokio::task::yield_now().await;
+
+                    let client_key = client_key_for_response(&response).unwrap();
+                    let mut guard = stores.transactions(response.call_id()).await;
+                    let tx_key = guard.find_by_client_key(&client_key).expect("işlem bulunmalı");
+                    guard.on_client_response(&tx_key, &response, 180);
+                    let tx_info = guard.get(&tx_key).expect("işlem bulunmalı");
+                    stores.dialogs(response.call_id()).await.on_invite_response(&tx_key, tx_info, &response, 180);
+                    guard.on_server_response(&tx_key, 180, b"SIP/2.0 180 Ringing\r\n\r\n");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let elapsed = started.elapsed();
+    timers.abort();
+    elapsed
+}
+
+fn main() {
+    std::env::set_var("SIP_SIGNALING_TARGET_UDP_URL", "10.0.0.1:5060");
+    std::env::set_var("SIP_GATEWAY_PUBLIC_IP", "192.0.2.1");
+    let config = Arc::new(AppConfig::load_from_env().expect("benchmark yapılandırması"));
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    println!("{} çekirdek, görev başına eşit bölünmüş {} çağrı", std::thread::available_parallelism().map_or(1, |n| n.get()), TOTAL_CALLS);
+    for tasks in TASK_COUNTS {
+        for sharded in [false, true] {
+            let mut best = Duration::MAX;
+            let mut name = "";
+            for _ in 0..ROUNDS {
+                let stores = Arc::new(if sharded {
+                    Stores::Sharded {
+                        transactions: Sharded::new(DEFAULT_SHARD_COUNT, TransactionStore::new),
+                        dialogs: Sharded::new(DEFAULT_SHARD_COUNT, DialogStore::new),
+                    }
+                } else {
+                    Stores::Global {
+                        transactions: Arc::new(Mutex::new(TransactionStore::new())),
+                        dialogs: Arc::new(Mutex::new(DialogStore::new())),
+                    }
+                });
+                name = stores.name();
+                best = best.min(runtime.block_on(run(stores, tasks, config.clone())));
+            }
+            println!(
+                "{:<9} {:>3} görev: {:.2?}, {:.0} çağrı/sn",
+                name,
+                tasks,
+                best,
+                TOTAL_CALLS as f64 / best.as_secs_f64()
+            );
+        }
+    }
+}
\ No newline at end of file
@@ -1,105 +1,131 @@
-// sentiric-sip-gateway-service/src/app.rs
-use crate::config::AppConfig;
-use crate::network;
-use crate::sip;
-use anyhow::{Context, Result};
-use std::convert::Infallible;
-use std::env;
-use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::select;
-use tokio::signal;
-use tokio::task::JoinHandle;
-use tracing::{error, info, warn};
-use tracing_subscriber::{fmt, prelude::*, EnvFilter, Registry};
-use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Response, Server, StatusCode};
-
-pub struct App {
-    config: Arc<AppConfig>,
-}
-
-async fn health_check_handler(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
-    // Sadece /healthz yolunu kontrol etmiyoruz, çünkü bu sunucu sadece o amaçla var.
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/json")
-        .body(Body::from(r#"{"status":"ok"}"#))
-        .unwrap())
-}
-
-fn spawn_http_server(config: Arc<AppConfig>) -> (JoinHandle<()>, tokio::sync::oneshot::Sender<()>) {
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    let handle = tokio::spawn(async move {
-        let addr = SocketAddr::from(([0, 0, 0, 0], config.http_port));
-        let make_svc = make_service_fn(|_conn| async {
-            Ok::<_, Infallible>(service_fn(health_check_handler))
-        });
-
-        let server = Server::bind(&addr)
-            .serve(make_svc)
-            .with_graceful_shutdown(async {
-                rx.await.ok();
-            });
-
-        info!(address = %addr, "HTTP sağlık kontrol sunucusu başlatıldı.");
-        if let Err(e) = server.await {
-            error!(error = %e, "HTTP sunucusu hatası.");
-        }
-    });
-    (handle, tx)
-}
-
-impl App {
-    pub async fn bootstrap() -> Result<Self> {
-        dotenvy::dotenv().ok();
-        let config = Arc::new(AppConfig::load_from_env().context("Konfigürasyon dosyası yüklenemedi")?);
-
-        let rust_log_env = env::var("RUST_LOG")
-            .unwrap_or_else(|_| "info,h2=warn,hyper=warn,tower=warn,rustls=warn".to_string());
-        
-        let env_filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&rust_log_env))?;
-        let subscriber = Registry::default().with(env_filter);
-        
-        if config.env == "development" {
-            subscriber.with(fmt::layer().with_target(true).with_line_number(true)).init();
-        } else {
-            subscriber.with(fmt::layer().json().with_current_span(true).with_span_list(true)).init();
-        }
-
-        info!(
-            service_name = "sentiric-sip-gateway-service",
-            version = %config.service_version,
-            commit = %config.git_commit,
-            build_date = %config.build_date,
-            profile = %config.env,
-            "🚀 Servis başlatılıyor..."
-        );
-        Ok(Self { config })
-    }
-
-    pub async fn run(self) -> Result<()> {
-        let transactions = sip::transaction::new_transaction_manager();
-        let cleanup_task = tokio::spawn(sip::transaction::cleanup_old_transactions(transactions.clone()));
-
-        let (http_server_handle, http_shutdown_tx) = spawn_http_server(self.config.clone());
-        let network_task = network::listen_and_process(self.config.clone(), transactions);
-
-        select! {
-            res = network_task => {
-                if let Err(e) = res { error!(error = %e, "Kritik UDP ağ hatası."); }
-            },
-            res = http_server_handle => {
-                if let Err(e) = res { error!(error = %e, "Kritik HTTP sunucu hatası."); }
-            },
-            _ = signal::ctrl_c() => {
-                warn!("Kapatma sinyali (Ctrl+C) alındı.");
-            }
-        }
-        
-        let _ = http_shutdown_tx.send(());
-        cleanup_task.abort();
-        info!("✅ Servis başarıyla kapatıldı.");
-        Ok(())
-    }
+// sentiric-sip-gateway-service/src/app.rs
+use crate::config::AppConfig;
+use crate::metrics::METRICS;
+use crate::network;
+use crate::sip;
+use anyhow::{Context, Result};
+use std::convert::Infallible;
+use std::env;
+use std::sync::Arc;
+use tokio::select;
+use tokio::signal;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter, Registry};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+
+pub struct App {
+    config: Arc<AppConfig>,
+}
+
+async fn health_check_handler(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    // Bu sunucu yalnızca sağlık kontrolü ve metrikler için var; diğer tüm yollar sağlık yanıtı döner.
+    if req.uri().path() == "/metrics" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(METRICS.render()))
+            .unwrap());
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(r#"{"status":"ok"}"#))
+        .unwrap())
+}
+
+fn spawn_http_server(config: Arc<AppConfig>, listener: std::net::TcpListener) -> (JoinHandle<()>, tokio::sync::oneshot::Sender<()>) {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let handle = tokio::spawn(async move {
+        let addr = config.http_listen_addr;
+        let make_svc = make_service_fn(|_conn| async {
+            Ok::<_, Infallible>(service_fn(health_check_handler))
+        });
+
+        let builder = match Server::from_tcp(listener) {
+            Ok(builder) => builder,
+            Err(e) => {
+                error!(error = %e, address = %addr, "HTTP sunucusu başlatılamadı.");
+                return;
+            }
+        };
+        let server = builder
+            .serve(make_svc)
+            .with_graceful_shutdown(async {
+                rx.await.ok();
+            });
+
+        info!(address = %addr, "HTTP sağlık kontrol sunucusu başlatıldı.");
+        if let Err(e) = server.await {
+            error!(error = %e, "HTTP sunucusu hatası.");
+        }
+    });
+    (handle, tx)
+}
+
+impl App {
+    pub async fn bootstrap() -> Result<Self> {
+        dotenvy::dotenv().ok();
+        let config = Arc::new(AppConfig::load_from_env().context("Konfigürasyon dosyası yüklenemedi")?);
+
+        let rust_log_env = env::var("RUST_LOG")
+            .unwrap_or_else(|_| "info,h2=warn,hyper=warn,tower=warn,rustls=warn".to_string());
+        
+        let env_filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&rust_log_env))?;
+        let subscriber = Registry::default().with(env_filter);
+        
+        if config.env == "development" {
+            subscriber.with(fmt::layer().with_target(true).with_line_number(true)).init();
+        } else {
+            subscriber.with(fmt::layer().json().with_current_span(true).with_span_list(true)).init();
+        }
+
+        info!(
+            service_name = "sentiric-sip-gateway-service",
+            version = %config.service_version,
+            commit = %config.git_commit,
+            build_date = %config.build_date,
+            profile = %config.env,
+            "🚀 Servis başlatılıyor..."
+        );
+        Ok(Self { config })
+    }
+
+    pub async fn run(self) -> Result<()> {
+        let transactions = sip::transaction::new_transaction_manager();
+        let dialogs = sip::dialog::new_dialog_manager(&self.config);
+        let listeners = network::bind_transport(&self.config).await?;
+        let http_listener = network::bind_http_listener(&self.config)?;
+        let transport = listeners.transport.clone();
+        let timer_task = tokio::spawn(sip::transaction::run_timers(transactions.clone(), transport.clone(), self.config.clone()));
+        let dialog_task = tokio::spawn(sip::dialog::run_expiry(
+            dialogs.clone(),
+            transactions.clone(),
+            transport,
+            self.config.clone(),
+        ));
+
+        let (http_server_handle, http_shutdown_tx) = spawn_http_server(self.config.clone(), http_listener);
+        let network_task = network::listen_and_process(listeners, self.config.clone(), transactions, dialogs);
+
+        select! {
+            res = network_task => {
+                if let Err(e) = res { error!(error = %e, "Kritik UDP ağ hatası."); }
+            },
+            res = http_server_handle => {
+                if let Err(e) = res { error!(error = %e, "Kritik HTTP sunucu hatası."); }
+            },
+            _ = signal::ctrl_c() => {
+                warn!("Kapatma sinyali (Ctrl+C) alındı.");
+            }
+        }
+        
+        let _ = http_shutdown_tx.send(());
+        timer_task.abort();
+        dialog_task.abort();
+        info!("✅ Servis başarıyla kapatıldı.");
+        Ok(())
+    }
 }
\ No newline at end of file
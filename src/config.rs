@@ -1,53 +1,256 @@
-// sentiric-sip-gateway-service/src/config.rs
-use anyhow::{Context, Result};
-use std::env;
-use std::net::{IpAddr, SocketAddr};
-
-#[derive(Debug)]
-pub struct AppConfig {
-    pub listen_addr: SocketAddr,
-    pub http_port: u16,
-    pub target_addr: String,
-    pub public_ip: IpAddr,
-    pub public_port: u16,
-    pub env: String,
-    pub service_version: String,
-    pub git_commit: String,
-    pub build_date: String,
-}
-
-impl AppConfig {
-    pub fn load_from_env() -> Result<Self> {
-        let listen_port_str = env::var("SIP_GATEWAY_UDP_PORT").unwrap_or_else(|_| "5060".to_string());
-        let listen_port = listen_port_str.parse::<u16>()?;
-
-        let http_port_str = env::var("SIP_GATEWAY_HTTP_PORT").unwrap_or_else(|_| "13010".to_string());
-        let http_port = http_port_str.parse::<u16>()?;
-
-        let target_addr = env::var("SIP_SIGNALING_TARGET_UDP_URL")
-            .context("ZORUNLU: SIP_SIGNALING_TARGET_UDP_URL eksik")?;
-        
-        let public_ip_str = env::var("SIP_GATEWAY_PUBLIC_IP")
-            .context("ZORUNLU: SIP_GATEWAY_PUBLIC_IP (gateway'in genel IP'si) eksik")?;
-        let public_ip = public_ip_str.parse::<IpAddr>()?;
-        
-        let listen_addr_str = format!("0.0.0.0:{}", listen_port);
-        let listen_addr = listen_addr_str.parse::<SocketAddr>().unwrap();
-
-        let service_version = env::var("SERVICE_VERSION").unwrap_or_else(|_| "0.1.0".to_string());
-        let git_commit = env::var("GIT_COMMIT").unwrap_or_else(|_| "unknown".to_string());
-        let build_date = env::var("BUILD_DATE").unwrap_or_else(|_| "unknown".to_string());
-
-        Ok(AppConfig {
-            listen_addr,
-            http_port,
-            target_addr,
-            public_ip,
-            public_port: listen_port,
-            env: env::var("ENV").unwrap_or_else(|_| "production".to_string()),
-            service_version,
-            git_commit,
-            build_date,
-        })
-    }
+// sentiric-sip-gateway-service/src/config.rs
+use crate::transport::tls::TlsClientAuth;
+use crate::transport::{Leg, Peer, Transport};
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// İç servislere bakan ayrı SIP bacağı: iç ağ arayüzündeki UDP soketi ve iç servislere duyurulan adres.
+#[derive(Debug, Clone, Copy)]
+pub struct InternalLeg {
+    pub listen_addr: SocketAddr,
+    /// Gateway'in iç servislere gönderdiği mesajların `Via` ve `Contact` başlıklarına yazılan adres.
+    pub advertised_addr: SocketAddr,
+}
+
+#[derive(Debug)]
+pub struct AppConfig {
+    pub listen_addr: SocketAddr,
+    pub tcp_listen_addr: SocketAddr,
+    pub tls_listen_addr: SocketAddr,
+    pub ws_listen_addr: SocketAddr,
+    /// WSS dinleyicisinin adresi; yalnızca `SIP_GATEWAY_WSS_PORT` verilmişse açılır ve TLS sunucu sertifikası gerektirir.
+    pub wss_listen_addr: Option<SocketAddr>,
+    pub http_listen_addr: SocketAddr,
+    pub target_addr: String,
+    /// Yalnızca `SIP_GATEWAY_INTERNAL_UDP_PORT` verilmişse açılır. Açıksa iç bacaktan gelen her istek
+    /// iç servislerden, diğer dinleyicilerden gelen her istek operatörlerden gelmiş sayılır.
+    pub internal_leg: Option<InternalLeg>,
+    /// `SIP_GATEWAY_PUBLIC_IP` içindeki ilk adres; karşı tarafın adres ailesi için ayrı bir adres verilmemişse kullanılır.
+    pub public_ip: IpAddr,
+    /// Diğer adres ailesinin (IPv4 veya IPv6) duyurulan adresi.
+    pub public_ip_secondary: Option<IpAddr>,
+    pub public_port: u16,
+    pub public_tcp_port: u16,
+    pub public_tls_port: u16,
+    pub public_ws_port: u16,
+    /// Gateway'in TLS sunucu sertifikası (PEM, zincir dahil). Anahtar dosyasıyla birlikte verilirse TLS dinleyicisi açılır.
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    /// Giden TLS bağlantılarında karşı tarafın ve istenirse istemci sertifikalarının doğrulandığı CA paketi (PEM).
+    pub tls_ca_file: Option<String>,
+    pub tls_client_auth: TlsClientAuth,
+    pub env: String,
+    /// Paketlerin Call-ID'ye göre dağıtıldığı sıralı kuyruk (işçi) sayısı.
+    pub worker_count: usize,
+    /// Tek bir kuyrukta bekleyebilecek en fazla paket; kuyruk doluysa yeni paketler atılır.
+    pub worker_queue_depth: usize,
+    /// Aynı anda işlenebilecek en fazla paket sayısı (tüm kuyruklar için).
+    pub max_concurrent_packets: usize,
+    /// Bu süre boyunca hiç veri okunmayan TCP, TLS ve WebSocket bağlantıları kapatılır.
+    pub stream_idle_timeout: Duration,
+    /// Aynı anda açık tutulabilecek en fazla gelen TCP, TLS ve WebSocket bağlantısı (tüm dinleyiciler için).
+    pub max_stream_connections: usize,
+    /// Oturum zamanlayıcısı olmayan onaylanmış bir diyalogda bu süre boyunca diyalog içi istek
+    /// görülmezse gateway her iki tarafa BYE gönderir. `None` (varsayılan) ise bu süre uygulanmaz.
+    pub dialog_idle_timeout: Option<Duration>,
+    pub service_version: String,
+    pub git_commit: String,
+    pub build_date: String,
+}
+
+impl AppConfig {
+    pub fn load_from_env() -> Result<Self> {
+        let listen_port_str = env::var("SIP_GATEWAY_UDP_PORT").unwrap_or_else(|_| "5060".to_string());
+        let listen_port = listen_port_str.parse::<u16>()?;
+
+        // TCP dinleyicisi varsayılan olarak UDP ile aynı port numarasını kullanır.
+        let tcp_port = match env::var("SIP_GATEWAY_TCP_PORT") {
+            Ok(value) => value.parse::<u16>()?,
+            Err(_) => listen_port,
+        };
+
+        let tls_port = env::var("SIP_GATEWAY_TLS_PORT").unwrap_or_else(|_| "5061".to_string()).parse::<u16>()?;
+        let ws_port = env::var("SIP_GATEWAY_WS_PORT").unwrap_or_else(|_| "13013".to_string()).parse::<u16>()?;
+        let wss_port = env::var("SIP_GATEWAY_WSS_PORT").ok().map(|v| v.parse::<u16>()).transpose()?;
+        let tls_cert_file = env::var("SIP_GATEWAY_TLS_CERT_FILE").ok().filter(|v| !v.is_empty());
+        let tls_key_file = env::var("SIP_GATEWAY_TLS_KEY_FILE").ok().filter(|v| !v.is_empty());
+        let tls_ca_file = env::var("SIP_GATEWAY_TLS_CA_FILE").ok().filter(|v| !v.is_empty());
+        let tls_client_auth_str = env::var("SIP_GATEWAY_TLS_CLIENT_AUTH").unwrap_or_else(|_| "none".to_string());
+        let tls_client_auth = TlsClientAuth::from_env_value(&tls_client_auth_str)
+            .with_context(|| format!("Geçersiz SIP_GATEWAY_TLS_CLIENT_AUTH değeri: '{}' (none, optional veya required olmalı)", tls_client_auth_str))?;
+
+        let http_port_str = env::var("SIP_GATEWAY_HTTP_PORT").unwrap_or_else(|_| "13010".to_string());
+        let http_port = http_port_str.parse::<u16>()?;
+
+        // SIP dinleyicilerinin bağlanacağı adres. `::` (veya `[::]`) çift yığınlı (dual-stack) dinleme içindir.
+        let bind_ip = parse_ip(&env::var("SIP_GATEWAY_BIND_ADDR").unwrap_or_else(|_| "0.0.0.0".to_string()))
+            .context("Geçersiz SIP_GATEWAY_BIND_ADDR değeri")?;
+        // HTTP sunucusu varsayılan olarak SIP dinleyicileriyle aynı adrese bağlanır.
+        let http_bind_ip = match env::var("SIP_GATEWAY_HTTP_BIND_ADDR") {
+            Ok(value) => parse_ip(&value).context("Geçersiz SIP_GATEWAY_HTTP_BIND_ADDR değeri")?,
+            Err(_) => bind_ip,
+        };
+
+        let target_addr = env::var("SIP_SIGNALING_TARGET_UDP_URL")
+            .context("ZORUNLU: SIP_SIGNALING_TARGET_UDP_URL eksik")?;
+        
+        let public_ip_str = env::var("SIP_GATEWAY_PUBLIC_IP")
+            .context("ZORUNLU: SIP_GATEWAY_PUBLIC_IP (gateway'in genel IP'si) eksik")?;
+        // Çift yığınlı kurulumlarda IPv4 ve IPv6 adresleri virgülle ayrılarak birlikte verilebilir.
+        let mut public_ips = Vec::new();
+        for value in public_ip_str.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            public_ips.push(parse_ip(value).with_context(|| format!("Geçersiz SIP_GATEWAY_PUBLIC_IP adresi: '{}'", value))?);
+        }
+        let (public_ip, public_ip_secondary) = match public_ips.as_slice() {
+            [ip] => (*ip, None),
+            [first, second] if first.is_ipv4() != second.is_ipv4() => (*first, Some(*second)),
+            _ => bail!("SIP_GATEWAY_PUBLIC_IP bir adres veya virgülle ayrılmış bir IPv4 ve bir IPv6 adresi içermelidir"),
+        };
+
+        // İç bacak, iç ağ arayüzüne bağlanan ayrı bir UDP soketidir. Duyurulan adres verilmemişse
+        // bağlanılan adres kullanılır; bu durumda adres belirsiz (`0.0.0.0`, `::`) olamaz.
+        let internal_leg = match env::var("SIP_GATEWAY_INTERNAL_UDP_PORT") {
+            Ok(value) => {
+                let port = value.parse::<u16>().context("Geçersiz SIP_GATEWAY_INTERNAL_UDP_PORT değeri")?;
+                let internal_bind_ip = match env::var("SIP_GATEWAY_INTERNAL_BIND_ADDR") {
+                    Ok(value) => parse_ip(&value).context("Geçersiz SIP_GATEWAY_INTERNAL_BIND_ADDR değeri")?,
+                    Err(_) => bind_ip,
+                };
+                let internal_ip = match env::var("SIP_GATEWAY_INTERNAL_IP") {
+                    Ok(value) => parse_ip(&value).context("Geçersiz SIP_GATEWAY_INTERNAL_IP değeri")?,
+                    Err(_) if !internal_bind_ip.is_unspecified() => internal_bind_ip,
+                    Err(_) => bail!("SIP_GATEWAY_INTERNAL_BIND_ADDR belirli bir adres değilse SIP_GATEWAY_INTERNAL_IP verilmelidir"),
+                };
+                Some(InternalLeg {
+                    listen_addr: SocketAddr::new(internal_bind_ip, port),
+                    advertised_addr: SocketAddr::new(internal_ip, port),
+                })
+            }
+            Err(_) => None,
+        };
+
+        let listen_addr = SocketAddr::new(bind_ip, listen_port);
+        let http_listen_addr = SocketAddr::new(http_bind_ip, http_port);
+        let tcp_listen_addr = SocketAddr::new(listen_addr.ip(), tcp_port);
+        let tls_listen_addr = SocketAddr::new(listen_addr.ip(), tls_port);
+        let ws_listen_addr = SocketAddr::new(listen_addr.ip(), ws_port);
+        let wss_listen_addr = wss_port.map(|port| SocketAddr::new(listen_addr.ip(), port));
+
+        let worker_count = env::var("SIP_GATEWAY_WORKERS").unwrap_or_else(|_| "64".to_string()).parse::<usize>()?.max(1);
+        let worker_queue_depth = env::var("SIP_GATEWAY_WORKER_QUEUE_DEPTH").unwrap_or_else(|_| "1024".to_string()).parse::<usize>()?.max(1);
+        let max_concurrent_packets = env::var("SIP_GATEWAY_MAX_CONCURRENCY").unwrap_or_else(|_| "32".to_string()).parse::<usize>()?.max(1);
+        let stream_idle_timeout = Duration::from_secs(env::var("SIP_GATEWAY_STREAM_IDLE_TIMEOUT").unwrap_or_else(|_| "600".to_string()).parse::<u64>()?.max(1));
+        let max_stream_connections = env::var("SIP_GATEWAY_MAX_STREAM_CONNECTIONS").unwrap_or_else(|_| "4096".to_string()).parse::<usize>()?.max(1);
+        let dialog_idle_timeout = match env::var("SIP_GATEWAY_DIALOG_IDLE_TIMEOUT").unwrap_or_else(|_| "0".to_string()).parse::<u64>()? {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+
+        let service_version = env::var("SERVICE_VERSION").unwrap_or_else(|_| "0.1.0".to_string());
+        let git_commit = env::var("GIT_COMMIT").unwrap_or_else(|_| "unknown".to_string());
+        let build_date = env::var("BUILD_DATE").unwrap_or_else(|_| "unknown".to_string());
+
+        Ok(AppConfig {
+            listen_addr,
+            tcp_listen_addr,
+            tls_listen_addr,
+            ws_listen_addr,
+            wss_listen_addr,
+            http_listen_addr,
+            target_addr,
+            internal_leg,
+            public_ip,
+            public_ip_secondary,
+            public_port: listen_port,
+            public_tcp_port: tcp_port,
+            public_tls_port: tls_port,
+            public_ws_port: ws_port,
+            tls_cert_file,
+            tls_key_file,
+            tls_ca_file,
+            tls_client_auth,
+            env: env::var("ENV").unwrap_or_else(|_| "production".to_string()),
+            worker_count,
+            worker_queue_depth,
+            max_concurrent_packets,
+            stream_idle_timeout,
+            max_stream_connections,
+            dialog_idle_timeout,
+            service_version,
+            git_commit,
+            build_date,
+        })
+    }
+    /// Gateway'in Via ve Contact başlıklarında karşı tarafa duyurduğu adres. İç bacak yapılandırılmışsa
+    /// iç servislere iç bacağın adresi, diğer karşı taraflara taşıma katmanının genel adresi duyurulur.
+    pub fn advertised_addr(&self, peer: Peer) -> SocketAddr {
+        match (peer.leg, self.internal_leg) {
+            (Leg::Internal, Some(internal_leg)) => internal_leg.advertised_addr,
+            _ => SocketAddr::new(self.public_ip_for(peer.addr.ip()), self.advertised_port(peer.transport)),
+        }
+    }
+
+    /// Gateway'in Via ve Contact başlıklarında, verilen adresteki karşı tarafa duyurduğu IP:
+    /// karşı tarafla aynı adres ailesinden yapılandırılmış adres, yoksa birincil adres.
+    pub fn public_ip_for(&self, peer: IpAddr) -> IpAddr {
+        match self.public_ip_secondary {
+            Some(ip) if ip.is_ipv6() == peer.to_canonical().is_ipv6() => ip,
+            _ => self.public_ip,
+        }
+    }
+
+    /// Gateway'in Via ve Contact başlıklarında, verilen taşıma katmanı için duyurduğu port.
+    pub fn advertised_port(&self, transport: Transport) -> u16 {
+        match transport {
+            Transport::Udp => self.public_port,
+            Transport::Tcp => self.public_tcp_port,
+            Transport::Tls => self.public_tls_port,
+            Transport::Ws => self.public_ws_port,
+            Transport::Wss => self.wss_listen_addr.map_or(Transport::Wss.default_port(), |addr| addr.port()),
+        }
+    }
+}
+
+/// IP adresini çözümler; IPv6 adresleri köşeli parantez içinde de yazılabilir (`[::]`).
+fn parse_ip(value: &str) -> Result<IpAddr> {
+    Ok(value.trim().trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>()?)
+}
+#[cfg(test)]
+impl AppConfig {
+    /// Birim testlerinde kullanılan, ortam değişkenlerine bakmayan yapılandırma.
+    pub(crate) fn for_tests() -> Self {
+        let listen_addr: SocketAddr = "203.0.113.1:5060".parse().unwrap();
+        AppConfig {
+            listen_addr,
+            tcp_listen_addr: listen_addr,
+            tls_listen_addr: "203.0.113.1:5061".parse().unwrap(),
+            ws_listen_addr: "203.0.113.1:13013".parse().unwrap(),
+            wss_listen_addr: None,
+            http_listen_addr: "127.0.0.1:13010".parse().unwrap(),
+            target_addr: "10.0.0.1:5060".to_string(),
+            internal_leg: None,
+            public_ip: listen_addr.ip(),
+            public_ip_secondary: None,
+            public_port: 5060,
+            public_tcp_port: 5060,
+            public_tls_port: 5061,
+            public_ws_port: 13013,
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_ca_file: None,
+            tls_client_auth: TlsClientAuth::None,
+            env: "test".to_string(),
+            worker_count: 1,
+            worker_queue_depth: 16,
+            max_concurrent_packets: 1,
+            stream_idle_timeout: Duration::from_secs(600),
+            max_stream_connections: 16,
+            dialog_idle_timeout: None,
+            service_version: "test".to_string(),
+            git_commit: "test".to_string(),
+            build_date: "test".to_string(),
+        }
+    }
 }
\ No newline at end of file
@@ -1,27 +1,65 @@
-// sentiric-sip-gateway-service/src/error.rs
-use thiserror::Error;
-use std::net::{AddrParseError, SocketAddr};
-use std::num::ParseIntError;
-
-#[derive(Error, Debug)]
-pub enum GatewayError {
-    #[error("UDP soketi '{addr}' adresine bağlanamadı: {source}")]
-    SocketBindError { addr: SocketAddr, source: std::io::Error },
-
-    // DÜZELTME: Bu varyant artık kullanılmadığı için kaldırıldı.
-    // #[error("Yapılandırma hatası: {0}")]
-    // ConfigError(String),
-
-    #[error("UDP soketinden okuma hatası: {0}")]
-    SocketReadError(#[from] std::io::Error),
-
-    #[error("Geçersiz IP adresi: {0}")]
-    AddrParse(#[from] AddrParseError),
-
-    #[error("Geçersiz port numarası: {0}")]
-    PortParse(#[from] ParseIntError),
-    
-    #[allow(dead_code)]
-    #[error("Geçersiz UTF-8 SIP paketi alındı")]
-    InvalidUtf8,
+// sentiric-sip-gateway-service/src/error.rs
+use thiserror::Error;
+use std::net::{AddrParseError, SocketAddr};
+use std::num::ParseIntError;
+
+#[derive(Error, Debug)]
+pub enum GatewayError {
+    #[error("UDP soketi '{addr}' adresine bağlanamadı: {source}")]
+    SocketBindError { addr: SocketAddr, source: std::io::Error },
+
+    #[error("TCP dinleyicisi '{addr}' adresine bağlanamadı: {source}")]
+    TcpBindError { addr: SocketAddr, source: std::io::Error },
+
+    #[error("TLS yapılandırması yüklenemedi: {0}")]
+    TlsConfig(String),
+
+    #[error("UDP soketinden okuma hatası: {0}")]
+    SocketReadError(#[from] std::io::Error),
+
+    #[error("Geçersiz IP adresi: {0}")]
+    AddrParse(#[from] AddrParseError),
+
+    #[error("Geçersiz port numarası: {0}")]
+    PortParse(#[from] ParseIntError),
+    
+    #[error("Başlangıç satırı geçerli UTF-8 olmayan SIP paketi alındı")]
+    InvalidUtf8,
+
+    #[error("SIP paketi ayrıştırılamadı: {0}")]
+    Parse(#[from] SipParseError),
+}
+
+/// Bir SIP paketinin neden ayrıştırılamadığını veya geçersiz sayıldığını açıklar.
+/// `reason_phrase` ile karşı tarafa gönderilen 400 yanıtında kusuru belirtmek için kullanılır.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SipParseError {
+    #[error("zorunlu başlık eksik: {0}")]
+    MissingHeader(&'static str),
+
+    #[error("geçersiz başlangıç satırı: '{0}'")]
+    BadStartLine(String),
+
+    #[error("geçersiz CSeq başlığı: '{0}'")]
+    BadCSeq(String),
+
+    #[error("Content-Length ({declared}) gövde uzunluğuyla ({actual}) uyuşmuyor")]
+    ContentLengthMismatch { declared: String, actual: usize },
+
+    #[error("başlık satırı izin verilen boyutu aşıyor ({0} byte)")]
+    OversizedHeader(usize),
+}
+
+impl SipParseError {
+    /// Kusuru, 400 yanıtının durum satırında ve `Warning` başlığında kullanılacak
+    /// (karşı tarafın anlayacağı) İngilizce bir ifadeyle döndürür.
+    pub fn reason_phrase(&self) -> String {
+        match self {
+            SipParseError::MissingHeader(name) => format!("Missing Mandatory Header: {}", name),
+            SipParseError::BadStartLine(_) => "Malformed Request-Line".to_string(),
+            SipParseError::BadCSeq(_) => "Malformed CSeq Header".to_string(),
+            SipParseError::ContentLengthMismatch { .. } => "Content-Length Mismatch".to_string(),
+            SipParseError::OversizedHeader(_) => "Header Field Too Large".to_string(),
+        }
+    }
 }
\ No newline at end of file
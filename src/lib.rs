@@ -0,0 +1,11 @@
+// sentiric-sip-gateway-service/src/lib.rs
+// Modüller kütüphane olarak da dışa açılır; böylece ikili dosya (main.rs) ve
+// benchmark'lar (benches/) aynı kodu kullanır.
+pub mod app;
+pub mod config;
+pub mod error;
+pub mod metrics;
+pub mod network;
+pub mod sip;
+pub mod transport;
+pub mod worker;
\ No newline at end of file
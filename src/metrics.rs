@@ -0,0 +1,62 @@
+// File: src/metrics.rs
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Servis genelinde tutulan sayaçlar.
+/// HTTP sunucusunun `/metrics` yolundan Prometheus metin formatında sunulur.
+pub struct Metrics {
+    pub packets_received: AtomicU64,
+    pub packets_rejected_invalid_utf8: AtomicU64,
+    pub packets_rejected_malformed: AtomicU64,
+    pub packets_dropped_queue_full: AtomicU64,
+    /// Anlık değerler (gauge): kuyruklarda bekleyen ve işlenmekte olan paket sayısı.
+    pub packets_queued: AtomicU64,
+    pub packets_in_flight: AtomicU64,
+}
+
+pub static METRICS: Metrics = Metrics {
+    packets_received: AtomicU64::new(0),
+    packets_rejected_invalid_utf8: AtomicU64::new(0),
+    packets_rejected_malformed: AtomicU64::new(0),
+    packets_dropped_queue_full: AtomicU64::new(0),
+    packets_queued: AtomicU64::new(0),
+    packets_in_flight: AtomicU64::new(0),
+};
+
+impl Metrics {
+    pub fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn decrement(gauge: &AtomicU64) {
+        gauge.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Sayaçları Prometheus metin formatına dönüştürür.
+    pub fn render(&self) -> String {
+        let counters = [
+            ("sip_gateway_packets_received_total", "Alınan toplam SIP paketi sayısı", &self.packets_received),
+            ("sip_gateway_packets_rejected_invalid_utf8_total", "Başlangıç satırı UTF-8 olmadığı için reddedilen paket sayısı", &self.packets_rejected_invalid_utf8),
+            ("sip_gateway_packets_rejected_malformed_total", "Ayrıştırılamadığı veya doğrulanamadığı için reddedilen paket sayısı", &self.packets_rejected_malformed),
+            ("sip_gateway_packets_dropped_queue_full_total", "İşçi kuyruğu dolu olduğu için atılan paket sayısı", &self.packets_dropped_queue_full),
+        ];
+        let gauges = [
+            ("sip_gateway_packets_queued", "İşçi kuyruklarında bekleyen paket sayısı", &self.packets_queued),
+            ("sip_gateway_packets_in_flight", "Şu anda işlenmekte olan paket sayısı", &self.packets_in_flight),
+        ];
+
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+        }
+        for (name, help, gauge) in gauges {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, gauge.load(Ordering::Relaxed));
+        }
+        out
+    }
+}
\ No newline at end of file
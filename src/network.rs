@@ -1,61 +1,242 @@
-// File: src/network.rs
-use crate::config::AppConfig;
-use crate::error::GatewayError;
-use crate::sip::handler;
-use crate::sip::transaction::Transactions;
-use std::io::ErrorKind;
-use std::sync::Arc;
-use tokio::net::UdpSocket;
-use tracing::{error, warn};
-
-pub async fn listen_and_process(
-    config: Arc<AppConfig>,
-    transactions: Transactions,
-) -> Result<(), GatewayError> {
-    let sock = UdpSocket::bind(config.listen_addr)
-        .await
-        .map_err(|e| GatewayError::SocketBindError {
-            addr: config.listen_addr,
-            source: e,
-        })?;
-    let sock = Arc::new(sock);
-
-    let mut buf = [0; 65535];
-    loop {
-        let (len, remote_addr) = match sock.recv_from(&mut buf).await {
-            Ok(result) => result,
-            Err(e) => {
-                // =========================================================================
-                //   SON LOG İYİLEŞTİRMESİ BURADA
-                // =========================================================================
-                if e.kind() == ErrorKind::ConnectionReset {
-                    warn!(
-                        error_kind = ?e.kind(),
-                        "Ağ dinleme hatası (ConnectionReset): Bu durum genellikle ulaşılamayan bir hedefe (örn: kapalı sip-signaling) paket gönderildikten sonra oluşur. Dinleyici devam ediyor."
-                    );
-                    continue; 
-                }
-                // =========================================================================
-                
-                error!(error = %e, "Soketten okuma sırasında kritik bir hata oluştu. Servis durdurulacak.");
-                return Err(e.into());
-            }
-        };
-        
-        let packet_str = match std::str::from_utf8(&buf[..len]) {
-            Ok(s) => s.to_string(),
-            Err(_) => {
-                warn!(source = %remote_addr, "UTF-8 olmayan bir paket alındı, atlanıyor.");
-                continue; 
-            }
-        };
-
-        let sock_clone = Arc::clone(&sock);
-        let transactions_clone = transactions.clone();
-        let config_clone = Arc::clone(&config);
-        
-        tokio::spawn(async move {
-            handler::handle_packet(&packet_str, remote_addr, &sock_clone, &transactions_clone, &config_clone).await;
-        });
-    }
+// File: src/network.rs
+use crate::config::AppConfig;
+use crate::error::GatewayError;
+use crate::metrics::{Metrics, METRICS};
+use crate::sip::dialog::Dialogs;
+use crate::sip::message::has_valid_start_line;
+use crate::sip::transaction::Transactions;
+use crate::transport::tcp::{StreamLimits, TcpConnections};
+use crate::transport::tls::TlsContext;
+use crate::transport::ws::WsConnections;
+use crate::transport::{canonical_addr, ConnectionId, Peer, Received, TransportLayer};
+use crate::worker::WorkerPool;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// TCP, TLS ve WebSocket bağlantılarından okunan mesajların ağ döngüsüne teslim edildiği kanalın kapasitesi.
+const STREAM_INBOUND_DEPTH: usize = 1024;
+/// Kabul edilmeyi bekleyen bağlantı kuyruğu (tokio'nun `TcpListener::bind` değeri).
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Bağlanmış dinleyiciler: gönderim arayüzü, gelen TCP, TLS ve WebSocket bağlantılarının
+/// dinleyicileri ve bu bağlantılardan okunan mesajların kanalı.
+pub struct Listeners {
+    pub transport: Arc<TransportLayer>,
+    tcp_listener: TcpListener,
+    /// Sunucu sertifikası yapılandırılmamışsa TLS dinleyicisi açılmaz.
+    tls_listener: Option<TcpListener>,
+    ws_listener: TcpListener,
+    wss_listener: Option<TcpListener>,
+    stream_inbound: mpsc::Receiver<Received>,
+}
+
+impl Listeners {
+    /// TLS dinleyicisinin bağlandığı adres; port 0 verildiyse işletim sisteminin seçtiği portu içerir.
+    pub fn tls_local_addr(&self) -> Option<SocketAddr> {
+        self.tls_listener.as_ref().and_then(|listener| listener.local_addr().ok())
+    }
+}
+
+/// Gateway'in SIP UDP soketini, TCP ve WebSocket dinleyicilerini ve yapılandırılmışsa iç bacağın
+/// UDP soketini, TLS ve WSS dinleyicilerini açar.
+pub async fn bind_transport(config: &AppConfig) -> Result<Listeners, GatewayError> {
+    let udp_addrs: Vec<SocketAddr> =
+        [Some(config.listen_addr), config.internal_leg.map(|internal_leg| internal_leg.listen_addr)].into_iter().flatten().collect();
+    let sock = bind_udp_socket(config.listen_addr, &udp_addrs)?;
+    let internal_sock = match config.internal_leg {
+        Some(internal_leg) => Some(Arc::new(bind_udp_socket(internal_leg.listen_addr, &udp_addrs)?)),
+        None => None,
+    };
+    let tcp_addrs = tcp_listen_addrs(config);
+    let tcp_listener = bind_stream_listener(config.tcp_listen_addr, &tcp_addrs)?;
+    let ws_listener = bind_stream_listener(config.ws_listen_addr, &tcp_addrs)?;
+    let tls_context = TlsContext::load(config)?.map(Arc::new);
+    let can_accept_tls = tls_context.as_ref().is_some_and(|context| context.can_accept());
+    let tls_listener = if can_accept_tls { Some(bind_stream_listener(config.tls_listen_addr, &tcp_addrs)?) } else { None };
+    let wss_listener = match config.wss_listen_addr {
+        Some(_) if !can_accept_tls => {
+            return Err(GatewayError::TlsConfig("WSS dinleyicisi için TLS sunucu sertifikası gereklidir".to_string()));
+        }
+        Some(addr) => Some(bind_stream_listener(addr, &tcp_addrs)?),
+        None => None,
+    };
+    info!(
+        udp = %config.listen_addr,
+        internal_udp = ?config.internal_leg.map(|internal_leg| internal_leg.listen_addr),
+        tcp = %config.tcp_listen_addr,
+        tls = ?tls_listener.as_ref().map(|_| config.tls_listen_addr),
+        ws = %config.ws_listen_addr,
+        wss = ?config.wss_listen_addr,
+        "SIP dinleyicileri açıldı."
+    );
+    let (inbound_tx, stream_inbound) = mpsc::channel(STREAM_INBOUND_DEPTH);
+    let limits = StreamLimits::new(config.stream_idle_timeout, config.max_stream_connections);
+    let tcp = Arc::new(TcpConnections::new(inbound_tx.clone(), None, limits.clone()));
+    let ws = Arc::new(WsConnections::new(inbound_tx.clone(), None, limits.clone()));
+    let wss = wss_listener.as_ref().map(|_| Arc::new(WsConnections::new(inbound_tx.clone(), tls_context.clone(), limits.clone())));
+    let tls = tls_context.map(|context| Arc::new(TcpConnections::new(inbound_tx, Some(context), limits)));
+    Ok(Listeners {
+        transport: Arc::new(TransportLayer::new(Arc::new(sock), internal_sock, tcp, tls, ws, wss)),
+        tcp_listener,
+        tls_listener,
+        ws_listener,
+        wss_listener,
+        stream_inbound,
+    })
+}
+
+/// HTTP sağlık kontrolü ve metrik sunucusunun dinleyicisini açar.
+pub fn bind_http_listener(config: &AppConfig) -> Result<std::net::TcpListener, GatewayError> {
+    let addr = config.http_listen_addr;
+    new_stream_listener(addr, &tcp_listen_addrs(config)).map_err(|e| GatewayError::TcpBindError { addr, source: e })
+}
+
+/// Gateway'in TCP üzerinden dinlediği tüm adresler (SIP akış dinleyicileri ve HTTP sunucusu).
+fn tcp_listen_addrs(config: &AppConfig) -> Vec<SocketAddr> {
+    [Some(config.tcp_listen_addr), Some(config.tls_listen_addr), Some(config.ws_listen_addr), config.wss_listen_addr, Some(config.http_listen_addr)]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// IPv6 bir soketin IPv4 trafiğini de alıp almayacağını (`IPV6_V6ONLY`) belirler; işletim
+/// sisteminin varsayılanına bırakılmaz. Aynı portta ayrıca bir IPv4 adrese de bağlanılıyorsa
+/// IPv4 trafiği o sokete kalır ve bu soket yalnızca IPv6 dinler; aksi halde `[::]` gibi
+/// adreslerde soket çift yığınlıdır.
+fn only_v6(addr: SocketAddr, listen_addrs: &[SocketAddr]) -> bool {
+    listen_addrs.iter().any(|other| other.is_ipv4() && other.port() == addr.port())
+}
+
+fn new_socket(addr: SocketAddr, ty: Type, protocol: Protocol, listen_addrs: &[SocketAddr]) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), ty, Some(protocol))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(only_v6(addr, listen_addrs))?;
+    }
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn bind_udp_socket(addr: SocketAddr, listen_addrs: &[SocketAddr]) -> Result<UdpSocket, GatewayError> {
+    new_socket(addr, Type::DGRAM, Protocol::UDP, listen_addrs)
+        .and_then(|socket| {
+            socket.bind(&addr.into())?;
+            UdpSocket::from_std(socket.into())
+        })
+        .map_err(|e| GatewayError::SocketBindError { addr, source: e })
+}
+
+fn bind_stream_listener(addr: SocketAddr, listen_addrs: &[SocketAddr]) -> Result<TcpListener, GatewayError> {
+    new_stream_listener(addr, listen_addrs)
+        .and_then(TcpListener::from_std)
+        .map_err(|e| GatewayError::TcpBindError { addr, source: e })
+}
+
+fn new_stream_listener(addr: SocketAddr, listen_addrs: &[SocketAddr]) -> io::Result<std::net::TcpListener> {
+    let socket = new_socket(addr, Type::STREAM, Protocol::TCP, listen_addrs)?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    Ok(socket.into())
+}
+
+pub async fn listen_and_process(
+    listeners: Listeners,
+    config: Arc<AppConfig>,
+    transactions: Transactions,
+    dialogs: Dialogs,
+) -> Result<(), GatewayError> {
+    let Listeners { transport, tcp_listener, tls_listener, ws_listener, wss_listener, mut stream_inbound } = listeners;
+    let workers = WorkerPool::spawn(transport.clone(), transactions, dialogs, config.clone());
+    tokio::spawn(transport.tcp().clone().accept_loop(tcp_listener));
+    if let (Some(tls), Some(listener)) = (transport.tls(), tls_listener) {
+        tokio::spawn(tls.clone().accept_loop(listener));
+    }
+    tokio::spawn(transport.ws().clone().accept_loop(ws_listener));
+    if let (Some(wss), Some(listener)) = (transport.wss(), wss_listener) {
+        tokio::spawn(wss.clone().accept_loop(listener));
+    }
+    let sock = transport.udp().clone();
+    let internal_sock = transport.internal_udp().cloned();
+    let mut buf = [0; 65535];
+    let mut internal_buf = [0; 65535];
+    loop {
+        tokio::select! {
+            received = sock.recv_from(&mut buf) => {
+                if let Some((len, remote_addr)) = udp_received(received)? {
+                    deliver(&workers, &buf[..len], Peer::udp(canonical_addr(remote_addr)), None);
+                }
+            }
+            received = recv_internal(internal_sock.as_deref(), &mut internal_buf) => {
+                if let Some((len, remote_addr)) = udp_received(received)? {
+                    deliver(&workers, &internal_buf[..len], Peer::internal(canonical_addr(remote_addr)), None);
+                }
+            }
+            Some(Received { packet, source, connection }) = stream_inbound.recv() => {
+                deliver(&workers, &packet, source, connection);
+            }
+        }
+    }
+}
+
+/// İç bacağın soketinden okur; iç bacak yapılandırılmamışsa hiç tamamlanmaz.
+async fn recv_internal(sock: Option<&UdpSocket>, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    match sock {
+        Some(sock) => sock.recv_from(buf).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// UDP okuma sonucunu değerlendirir. Geçici hatalarda `None` döner ve dinleme sürer;
+/// kritik hatalar servisi durdurur.
+fn udp_received(received: io::Result<(usize, SocketAddr)>) -> Result<Option<(usize, SocketAddr)>, GatewayError> {
+    match received {
+        Ok(result) => Ok(Some(result)),
+        Err(e) => {
+            // =========================================================================
+            //   SON LOG İYİLEŞTİRMESİ BURADA
+            // =========================================================================
+            if e.kind() == ErrorKind::ConnectionReset {
+                warn!(
+                    error_kind = ?e.kind(),
+                    "Ağ dinleme hatası (ConnectionReset): Bu durum genellikle ulaşılamayan bir hedefe (örn: kapalı sip-signaling) paket gönderildikten sonra oluşur. Dinleyici devam ediyor."
+                );
+                return Ok(None);
+            }
+            // =========================================================================
+
+            error!(error = %e, "Soketten okuma sırasında kritik bir hata oluştu. Servis durdurulacak.");
+            Err(e.into())
+        }
+    }
+}
+
+/// Alınan bir paketi doğrular ve işçi kuyruğuna teslim eder.
+fn deliver(workers: &WorkerPool, packet: &[u8], source: Peer, connection: Option<ConnectionId>) {
+    Metrics::increment(&METRICS.packets_received);
+    // Bazı istemcilerin gönderdiği CRLF "keep-alive" paketleri (RFC 5626 §3.5.1) sessizce atlanır.
+    if packet.iter().all(u8::is_ascii_whitespace) {
+        return;
+    }
+    if let Err(e) = check_packet(packet) {
+        Metrics::increment(&METRICS.packets_rejected_invalid_utf8);
+        warn!(source = %source, error = %e, "SIP olarak yorumlanamayan paket reddedildi, atlanıyor.");
+        return;
+    }
+    workers.dispatch(packet.to_vec(), source, connection);
+}
+
+/// Paketin SIP olarak yorumlanabilir olup olmadığını kontrol eder.
+/// Gövdenin ve başlık değerlerinin UTF-8 olması gerekmez (örn. ISUP içeren multipart gövdeler);
+/// yalnızca başlangıç satırı çözülemeyen paketler reddedilir.
+fn check_packet(packet: &[u8]) -> Result<(), GatewayError> {
+    if has_valid_start_line(packet) {
+        Ok(())
+    } else {
+        Err(GatewayError::InvalidUtf8)
+    }
 }
\ No newline at end of file
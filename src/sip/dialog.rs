@@ -0,0 +1,881 @@
+// File: src/sip/dialog.rs
+
+use crate::config::AppConfig;
+use crate::sip::message::SipMessage;
+use crate::sip::message_builder::build_dialog_request;
+use crate::sip::processor;
+use crate::sip::shard::{Sharded, DEFAULT_SHARD_COUNT};
+use crate::sip::timer::TimerWheel;
+use crate::sip::transaction::{Direction, TransactionInfo, TransactionKey, Transactions};
+use crate::sip::uri::{split_header_list, NameAddr};
+use crate::sip::via::new_branch;
+use crate::transport::{ConnectionId, Peer, TransportLayer};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// Nihai yanıt görülmeyen erken diyalogların son geçici yanıttan sonra tutulacağı en uzun süre;
+/// süre dolduğunda diyalog sessizce silinir.
+const EARLY_DIALOG_TIMEOUT: Duration = Duration::from_secs(4 * 60 * 60);
+/// Kabul edilen en kısa `Session-Expires` değeri; daha kısa istekler 422 ile reddedilir (RFC 4028 §5).
+pub const MIN_SESSION_EXPIRES: Duration = Duration::from_secs(90);
+/// Dikkate alınan en uzun `Session-Expires` değeri; daha uzun süreler bu değere indirilir.
+const MAX_SESSION_EXPIRES: Duration = Duration::from_secs(24 * 60 * 60);
+/// Diyalog zamanlayıcı çarkının adımı; süresi dolan diyaloglar bu aralıkla işlenir.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+const EXPIRY_SLOTS: usize = 512;
+
+/// Diyalog kimliği (RFC 3261 §12): Call-ID ve iki tarafın etiketleri.
+/// `local_tag` iç ağ tarafının (signaling-service), `remote_tag` operatör tarafının etiketidir.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DialogId {
+    pub call_id: String,
+    pub local_tag: String,
+    pub remote_tag: String,
+}
+
+impl DialogId {
+    /// Diyalog içi bir istekten diyalog kimliğini hesaplar. Operatörden gelen isteklerde
+    /// From etiketi uzak, To etiketi yerel taraftır; iç ağdan gelenlerde tersidir.
+    /// To etiketi olmayan (diyalog dışı) isteklerde `None` döner.
+    pub fn for_request(msg: &SipMessage, direction: Direction) -> Option<Self> {
+        let from_tag = tag_of(msg, "From")?;
+        let to_tag = tag_of(msg, "To")?;
+        let (local_tag, remote_tag) = match direction {
+            Direction::Inbound => (to_tag, from_tag),
+            Direction::Outbound => (from_tag, to_tag),
+        };
+        Some(DialogId {
+            call_id: msg.headers.get("Call-ID")?.to_string(),
+            local_tag,
+            remote_tag,
+        })
+    }
+
+    /// Operatörden gelen bir INVITE'a iç ağın verdiği yanıttan diyalog kimliğini hesaplar.
+    pub fn for_invite_response(msg: &SipMessage) -> Option<Self> {
+        Some(DialogId {
+            call_id: msg.headers.get("Call-ID")?.to_string(),
+            local_tag: tag_of(msg, "To")?,
+            remote_tag: tag_of(msg, "From")?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DialogState {
+    /// 1xx yanıtla oluşmuş, henüz onaylanmamış diyalog.
+    Early,
+    Confirmed,
+}
+
+/// Bir çağrı boyunca saklanan diyalog durumu.
+#[derive(Clone, Debug)]
+pub struct Dialog {
+    pub state: DialogState,
+    /// Operatörün isteklerinin geldiği adres ve taşıma katmanı; iç ağdan gelen diyalog içi istekler buraya gönderilir.
+    pub remote_addr: Peer,
+    /// Operatörün mesajlarının geldiği akış bağlantısı (TCP, TLS, WebSocket). Operatöre giden
+    /// diyalog içi istekler bu bağlantıdan gönderilir; UDP'de `None`.
+    pub remote_connection: Option<ConnectionId>,
+    /// Diyaloğun iç servis tarafının adresi, taşıma katmanı ve bacağı; operatörden gelen diyalog
+    /// içi istekler ve gateway'in iç servise gönderdiği BYE buraya gider.
+    pub local_addr: Peer,
+    /// Operatörün Contact URI'si (remote target, RFC 3261 §12.1.1). Hedef yenileyen isteklerle güncellenir.
+    pub remote_target: Option<String>,
+    /// İç servisin Contact URI'si; gateway'in iç servise gönderdiği isteklerde Request-URI olarak kullanılır.
+    pub local_target: Option<String>,
+    /// İç servis tarafının etiketli From/To değeri (`<sip:...>;tag=...`).
+    pub local_party: String,
+    /// Operatör tarafının etiketli From/To değeri.
+    pub remote_party: String,
+    /// Diyaloğu başlatan INVITE'taki `Record-Route` başlıkları, sırasıyla (route set).
+    pub route_set: Vec<String>,
+    /// İç ağdan operatöre iletilen son diyalog içi isteğin CSeq numarası.
+    pub local_cseq: u32,
+    /// Operatörden gelen son diyalog içi isteğin CSeq numarası.
+    pub remote_cseq: u32,
+    /// Anlaşılan oturum zamanlayıcısı; yoksa onaylanmış diyalog BYE ile ya da, yapılandırılmışsa,
+    /// etkinlik süresi dolunca sonlanır.
+    pub session_timer: Option<SessionTimer>,
+    /// Operatöre iletilen ve henüz PRACK ile onaylanmamış son güvenilir geçici yanıt.
+    pub pending_prack: Option<PendingPrack>,
+    /// Çatallanan (forked) INVITE'a bu diyaloktan sonra 2xx veren ve gateway'in ACK + BYE ile
+    /// kapattığı operatör etiketleri.
+    pub refused_forks: Vec<String>,
+    pub last_activity: Instant,
+    pub created_at: Instant,
+    /// Çarktaki son zamanlayıcının kimliği; önceki zamanlayıcılar tetiklendiğinde yok sayılır.
+    expiry_generation: u64,
+}
+
+/// İç servisin operatöre gönderdiği ilk INVITE'a gelen yanıtın ne yapılacağını belirtir.
+pub enum InviteResponseAction {
+    /// Yanıt iç servise iletilmeli.
+    Forward,
+    /// Başka bir etiketle zaten onaylanmış bir çağrıya gelen fazladan 2xx (RFC 3261 §13.2.2.4):
+    /// iç servise iletilmez; gateway ACK gönderir, ilk kez görülüyorsa BYE ile kapatır.
+    RefuseFork { id: DialogId, dialog: Box<Dialog>, send_bye: bool },
+}
+
+/// PRACK bekleyen güvenilir geçici yanıtın RAck ile eşleştirilecek bilgileri (RFC 3262 §7.2).
+#[derive(Clone, Debug)]
+pub struct PendingPrack {
+    pub rseq: u32,
+    /// Yanıtın ait olduğu INVITE'ın CSeq numarası.
+    pub cseq: u32,
+    /// Yeniden iletimi yapan INVITE sunucu işleminin anahtarı.
+    pub invite_key: TransactionKey,
+}
+
+/// 2xx yanıttaki `Session-Expires` ile anlaşılan oturum zamanlayıcısı (RFC 4028 §9).
+/// Oturumu her iki taraf da yenileyebildiği için yenileyicinin (`refresher`) hangi taraf olduğu
+/// tutulmaz; yenileme yapan her 2xx süreyi baştan başlatır.
+#[derive(Clone, Debug)]
+pub struct SessionTimer {
+    pub interval: Duration,
+    pub refreshed_at: Instant,
+}
+
+impl SessionTimer {
+    /// Bir oturum yenileme isteğine (INVITE, UPDATE) verilen 2xx yanıttan zamanlayıcıyı çıkarır.
+    fn from_response(response: &SipMessage) -> Option<Self> {
+        let interval = session_expires(response)?;
+        Some(SessionTimer { interval, refreshed_at: Instant::now() })
+    }
+
+    /// Yenileme gelmezse oturumun sonlandırılacağı an: yenileyici olmayan tarafın
+    /// kullandığı `interval - min(32 sn, interval/3)` süresi (RFC 4028 §10).
+    fn deadline(&self) -> Instant {
+        let wait = self.interval - (self.interval / 3).min(Duration::from_secs(32));
+        self.refreshed_at
+            .checked_add(wait)
+            .unwrap_or_else(|| Instant::now() + MAX_SESSION_EXPIRES)
+    }
+}
+
+impl Dialog {
+    /// İç servisin operatöre gönderdiği INVITE'a gelen etiketli yanıttan diyalog oluşturur
+    /// (UAC tarafı, RFC 3261 §12.1.2): route set, yanıttaki `Record-Route` listesinin tersidir.
+    /// `connection`, yanıtın okunduğu bağlantıdır.
+    fn for_outbound_response(tx_info: &TransactionInfo, response: &SipMessage, connection: Option<ConnectionId>) -> Self {
+        let (local_cseq, _) = tx_info.original_request.cseq().unwrap_or_default();
+        Dialog {
+            state: DialogState::Early,
+            remote_addr: tx_info.client.target,
+            remote_connection: connection,
+            local_addr: processor::response_target(tx_info),
+            remote_target: response.headers.get("Contact").and_then(contact_uri),
+            local_target: contact_uri(&tx_info.original_contact_header),
+            local_party: response.headers.get("From").unwrap_or_default().to_string(),
+            remote_party: response.headers.get("To").unwrap_or_default().to_string(),
+            route_set: uac_route_set(response),
+            local_cseq,
+            remote_cseq: 0,
+            session_timer: None,
+            pending_prack: None,
+            refused_forks: Vec::new(),
+            last_activity: Instant::now(),
+            created_at: Instant::now(),
+            expiry_generation: 0,
+        }
+    }
+
+    /// Diyaloğun sonlanacağı an: oturum zamanlayıcısı varsa yenileme için son an, yoksa son
+    /// etkinlikten itibaren erken diyaloglar için sabit süre, onaylanmış diyaloglar için
+    /// `idle_timeout`. Etkinlik süresi kapalıysa onaylanmış diyalog kendiliğinden sonlanmaz.
+    fn expires_at(&self, idle_timeout: Option<Duration>) -> Option<Instant> {
+        match (&self.session_timer, self.state) {
+            (Some(timer), _) => Some(timer.deadline()),
+            (None, DialogState::Early) => Some(self.last_activity + EARLY_DIALOG_TIMEOUT),
+            (None, DialogState::Confirmed) => idle_timeout.map(|timeout| self.last_activity + timeout),
+        }
+    }
+
+    /// Bir oturum yenileme isteğine verilen 2xx ile oturum zamanlayıcısını yeniler.
+    /// Yanıtta `Session-Expires` yoksa oturum zamanlayıcısı kapanır (RFC 4028 §9).
+    fn refresh_session(&mut self, response: &SipMessage) {
+        self.session_timer = SessionTimer::from_response(response);
+        if let Some(timer) = &self.session_timer {
+            debug!(interval_secs = timer.interval.as_secs(), "Oturum zamanlayıcısı yenilendi.");
+        }
+    }
+}
+
+/// Çağrı süresince yaşayan diyalogları, kısa ömürlü işlem kayıtlarından ayrı olarak tutar.
+/// Her diyaloğun çarkta tek bir geçerli zamanlayıcısı bulunur; depo hiçbir zaman baştan sona taranmaz.
+/// Aynı çağrının çatallarına Call-ID dizini üzerinden ulaşılır.
+pub struct DialogStore {
+    dialogs: HashMap<DialogId, Dialog>,
+    by_call_id: HashMap<String, Vec<DialogId>>,
+    timers: TimerWheel<(DialogId, u64)>,
+    next_generation: u64,
+    /// Oturum zamanlayıcısı olmayan onaylanmış diyalogların etkinlik süresi; `None` ise kapalıdır.
+    idle_timeout: Option<Duration>,
+}
+
+pub type Dialogs = Arc<Sharded<DialogStore>>;
+
+pub fn new_dialog_manager(config: &AppConfig) -> Dialogs {
+    let idle_timeout = config.dialog_idle_timeout;
+    Arc::new(Sharded::new(DEFAULT_SHARD_COUNT, || DialogStore::with_idle_timeout(idle_timeout)))
+}
+
+impl Default for DialogStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DialogStore {
+    pub fn new() -> Self {
+        Self::with_idle_timeout(None)
+    }
+
+    /// Oturum zamanlayıcısı olmayan onaylanmış diyalogları `idle_timeout` boyunca diyalog içi
+    /// istek görülmezse sonlandıran depo oluşturur.
+    pub fn with_idle_timeout(idle_timeout: Option<Duration>) -> Self {
+        DialogStore {
+            dialogs: HashMap::new(),
+            by_call_id: HashMap::new(),
+            timers: TimerWheel::new(EXPIRY_INTERVAL, EXPIRY_SLOTS),
+            next_generation: 1,
+            idle_timeout,
+        }
+    }
+
+    /// Yeni diyaloğu depoya ve Call-ID dizinine ekler; diyalog zaten varsa mevcut kaydı döndürür.
+    fn entry(&mut self, id: &DialogId, create: impl FnOnce() -> Dialog) -> &mut Dialog {
+        let by_call_id = &mut self.by_call_id;
+        self.dialogs.entry(id.clone()).or_insert_with(|| {
+            by_call_id.entry(id.call_id.clone()).or_default().push(id.clone());
+            create()
+        })
+    }
+
+    /// Diyaloğu depodan ve Call-ID dizininden çıkarır.
+    fn take(&mut self, id: &DialogId) -> Option<Dialog> {
+        let dialog = self.dialogs.remove(id)?;
+        if let Some(forks) = self.by_call_id.get_mut(&id.call_id) {
+            forks.retain(|other| other != id);
+            if forks.is_empty() {
+                self.by_call_id.remove(&id.call_id);
+            }
+        }
+        Some(dialog)
+    }
+
+    /// Çağrının `matches` koşulunu sağlayan erken diyaloglarını siler. Yalnızca aynı Call-ID'ye
+    /// sahip çatallara bakılır.
+    fn remove_early(&mut self, call_id: &str, matches: impl Fn(&DialogId) -> bool) {
+        let early: Vec<DialogId> = self
+            .by_call_id
+            .get(call_id)
+            .into_iter()
+            .flatten()
+            .filter(|id| matches(id) && self.dialogs.get(*id).is_some_and(|dialog| dialog.state == DialogState::Early))
+            .cloned()
+            .collect();
+        for id in early {
+            self.take(&id);
+        }
+    }
+
+    /// Diyaloğun sonlanma zamanlayıcısını yeniden kurar; önceki zamanlayıcı geçersiz olur.
+    /// Diyaloğun sonlanacağı bir an yoksa yeni zamanlayıcı kurulmaz.
+    fn schedule_expiry(&mut self, id: &DialogId) {
+        let Some(dialog) = self.dialogs.get_mut(id) else {
+            return;
+        };
+        dialog.expiry_generation = self.next_generation;
+        self.next_generation += 1;
+        if let Some(expires_at) = dialog.expires_at(self.idle_timeout) {
+            let delay = expires_at.saturating_duration_since(Instant::now());
+            self.timers.schedule(delay, (id.clone(), dialog.expiry_generation));
+        }
+    }
+
+    /// Operatörden gelen bir INVITE'a iç ağın verdiği yanıtı diyalog katmanına uygular.
+    /// Etiketli 1xx yanıtlar erken diyalog, 2xx yanıtlar onaylanmış diyalog oluşturur;
+    /// 2xx olmayan nihai yanıtlar o INVITE'ın erken diyaloglarını sonlandırır (RFC 3261 §12.1, §13.2.2.4).
+    /// Güvenilir geçici yanıtlar (RFC 3262) PRACK ile eşleştirilmek üzere diyalogda saklanır.
+    pub fn on_invite_response(&mut self, tx_key: &TransactionKey, tx_info: &TransactionInfo, response: &SipMessage, status: u16) {
+        if status >= 300 {
+            let call_id = response.headers.get("Call-ID").unwrap_or_default();
+            let remote_tag = tag_of(response, "From").unwrap_or_default();
+            self.remove_early(call_id, |id| id.remote_tag == remote_tag);
+            return;
+        }
+        let Some(id) = DialogId::for_invite_response(response) else {
+            return;
+        };
+        let dialog = self.entry(&id, || {
+            debug!(call_id = %id.call_id, status, "Yeni diyalog oluşturuldu.");
+            let (remote_cseq, _) = tx_info.original_request.cseq().unwrap_or_default();
+            Dialog {
+                state: DialogState::Early,
+                remote_addr: tx_info.original_client,
+                remote_connection: tx_info.connection,
+                local_addr: tx_info.client.target,
+                remote_target: contact_uri(&tx_info.original_contact_header),
+                local_target: None,
+                local_party: response.headers.get("To").unwrap_or_default().to_string(),
+                remote_party: response.headers.get("From").unwrap_or_default().to_string(),
+                route_set: tx_info.record_route_headers.clone(),
+                local_cseq: 0,
+                remote_cseq,
+                session_timer: None,
+                pending_prack: None,
+                refused_forks: Vec::new(),
+                last_activity: Instant::now(),
+                created_at: Instant::now(),
+                expiry_generation: 0,
+            }
+        });
+        if let Some(target) = response.headers.get("Contact").and_then(contact_uri) {
+            dialog.local_target = Some(target);
+        }
+        if (200..300).contains(&status) {
+            dialog.state = DialogState::Confirmed;
+            dialog.refresh_session(response);
+        } else if let Some(rseq) = reliable_rseq(response) {
+            let (cseq, _) = response.cseq().unwrap_or_default();
+            dialog.pending_prack = Some(PendingPrack { rseq, cseq, invite_key: tx_key.clone() });
+        }
+        dialog.last_activity = Instant::now();
+        self.schedule_expiry(&id);
+    }
+
+    /// İç servisin operatöre gönderdiği INVITE'a gelen yanıtı diyalog katmanına uygular.
+    /// Çatallanan bir INVITE'ta her To etiketi ayrı bir erken diyalog oluşturur. İlk 2xx diyaloğu
+    /// onaylar ve diğer erken diyalogları kapatır; sonraki etiketlerden gelen 2xx'ler reddedilir.
+    /// re-INVITE yanıtları hedef yenileme olarak işlenir. `connection`, yanıtın okunduğu bağlantıdır.
+    pub fn on_outbound_invite_response(
+        &mut self,
+        tx_info: &TransactionInfo,
+        response: &SipMessage,
+        status: u16,
+        connection: Option<ConnectionId>,
+    ) -> InviteResponseAction {
+        let request = &tx_info.original_request;
+        if tag_of(request, "To").is_some() {
+            if (200..300).contains(&status) {
+                self.on_target_refresh_response(request, response, Direction::Outbound);
+            }
+            return InviteResponseAction::Forward;
+        }
+        let Some(id) = DialogId::for_request(response, Direction::Outbound) else {
+            return InviteResponseAction::Forward;
+        };
+        let local_tag = id.local_tag.clone();
+        let same_call = move |other: &DialogId| other.local_tag == local_tag;
+        if status >= 300 {
+            self.remove_early(&id.call_id, same_call);
+            return InviteResponseAction::Forward;
+        }
+        if (200..300).contains(&status) {
+            let accepted = self
+                .by_call_id
+                .get(&id.call_id)
+                .into_iter()
+                .flatten()
+                .find(|other| {
+                    same_call(other)
+                        && other.remote_tag != id.remote_tag
+                        && self.dialogs.get(*other).is_some_and(|dialog| dialog.state == DialogState::Confirmed)
+                })
+                .cloned();
+            if let Some(accepted) = accepted.and_then(|other| self.dialogs.get_mut(&other)) {
+                let send_bye = !accepted.refused_forks.contains(&id.remote_tag);
+                if send_bye {
+                    accepted.refused_forks.push(id.remote_tag.clone());
+                    info!(call_id = %id.call_id, remote_tag = %id.remote_tag, "Çatallanan INVITE'a fazladan 2xx geldi, ACK ve BYE ile kapatılıyor.");
+                } else {
+                    debug!(call_id = %id.call_id, remote_tag = %id.remote_tag, "Reddedilen 2xx yinelendi, ACK yeniden gönderiliyor.");
+                }
+                let dialog = Box::new(Dialog::for_outbound_response(tx_info, response, connection));
+                return InviteResponseAction::RefuseFork { id, dialog, send_bye };
+            }
+        }
+        let dialog = self.entry(&id, || {
+            debug!(call_id = %id.call_id, remote_tag = %id.remote_tag, status, "Giden çağrı için yeni diyalog oluşturuldu.");
+            Dialog::for_outbound_response(tx_info, response, connection)
+        });
+        if let Some(target) = response.headers.get("Contact").and_then(contact_uri) {
+            dialog.remote_target = Some(target);
+        }
+        dialog.last_activity = Instant::now();
+        if (200..300).contains(&status) {
+            // Erken diyaloğun route set'i 2xx yanıta göre yeniden hesaplanır (RFC 3261 §13.2.2.4).
+            if dialog.state == DialogState::Early {
+                dialog.state = DialogState::Confirmed;
+                dialog.route_set = uac_route_set(response);
+                dialog.refresh_session(response);
+            }
+            self.remove_early(&id.call_id, same_call);
+        }
+        self.schedule_expiry(&id);
+        InviteResponseAction::Forward
+    }
+
+    /// Operatörden gelen PRACK'in `RAck` değerini bekleyen güvenilir geçici yanıtla eşleştirir
+    /// (RFC 3262 §7.2). Eşleşirse yanıtı yeniden ileten INVITE işleminin anahtarı ve RSeq döner.
+    pub fn on_prack(&mut self, id: &DialogId, prack: &SipMessage) -> Option<(TransactionKey, u32)> {
+        let (rseq, cseq, method) = rack(prack)?;
+        let dialog = self.dialogs.get_mut(id)?;
+        let pending = dialog.pending_prack.as_ref()?;
+        if pending.rseq != rseq || pending.cseq != cseq || method != "INVITE" {
+            debug!(call_id = %id.call_id, rseq, cseq, "PRACK bekleyen güvenilir geçici yanıtla eşleşmedi.");
+            return None;
+        }
+        dialog.pending_prack.take().map(|pending| (pending.invite_key, pending.rseq))
+    }
+
+    /// Diyalog içi bir isteği kaydeder: CSeq sayacını ve son etkinlik zamanını günceller,
+    /// operatörden gelen hedef yenileyen isteklerde (re-INVITE, UPDATE) remote target'ı yeniler.
+    /// Operatörün isteği bir akış bağlantısından (`connection`) geldiyse diyalog bu bağlantıya
+    /// bağlanır. Diyalog bulunamazsa `None` döner.
+    pub fn on_request(&mut self, id: &DialogId, msg: &SipMessage, direction: Direction, connection: Option<ConnectionId>) -> Option<Dialog> {
+        let dialog = self.dialogs.get_mut(id)?;
+        let (cseq, _) = msg.cseq().unwrap_or_default();
+        match direction {
+            Direction::Inbound => {
+                dialog.remote_cseq = dialog.remote_cseq.max(cseq);
+                if connection.is_some() {
+                    dialog.remote_connection = connection;
+                }
+                if matches!(msg.method(), Some("INVITE" | "UPDATE")) {
+                    if let Some(target) = msg.headers.get("Contact").and_then(contact_uri) {
+                        dialog.remote_target = Some(target);
+                    }
+                }
+            }
+            Direction::Outbound => dialog.local_cseq = dialog.local_cseq.max(cseq),
+        }
+        dialog.last_activity = Instant::now();
+        Some(dialog.clone())
+    }
+
+    /// Hedef yenileyen bir isteğe (re-INVITE, UPDATE) gelen 2xx yanıttaki Contact ile yanıtı veren
+    /// tarafın target'ını (RFC 3261 §12.2.1.2) ve oturum zamanlayıcısını (RFC 4028 §9) yeniler.
+    /// `direction`, isteğin iletildiği yöndür.
+    pub fn on_target_refresh_response(&mut self, request: &SipMessage, response: &SipMessage, direction: Direction) {
+        let Some(id) = DialogId::for_request(request, direction) else {
+            return;
+        };
+        let Some(dialog) = self.dialogs.get_mut(&id) else {
+            return;
+        };
+        if let Some(target) = response.headers.get("Contact").and_then(contact_uri) {
+            match direction {
+                Direction::Outbound => dialog.remote_target = Some(target),
+                Direction::Inbound => dialog.local_target = Some(target),
+            }
+        }
+        dialog.refresh_session(response);
+        dialog.last_activity = Instant::now();
+        self.schedule_expiry(&id);
+    }
+
+    /// Diyaloğu sonlandırır (örn. BYE ile).
+    pub fn remove(&mut self, id: &DialogId) -> Option<Dialog> {
+        let dialog = self.take(id)?;
+        debug!(call_id = %id.call_id, duration_secs = dialog.created_at.elapsed().as_secs(), "Diyalog sonlandırıldı.");
+        Some(dialog)
+    }
+
+    /// Süresi dolan zamanlayıcıları işler. Etkinliği süren diyaloğun zamanlayıcısı kalan süreye
+    /// göre yeniden kurulur. Süresi dolan erken diyaloglar sessizce silinir; onaylanmış diyaloglar
+    /// silinip her iki tarafa BYE gönderilmek üzere döndürülür.
+    pub fn process_timers(&mut self, now: Instant) -> Vec<(DialogId, Dialog)> {
+        let mut expired = Vec::new();
+        for (id, generation) in self.timers.advance(now) {
+            let Some(dialog) = self.dialogs.get(&id) else {
+                continue;
+            };
+            if dialog.expiry_generation != generation {
+                continue;
+            }
+            match dialog.expires_at(self.idle_timeout) {
+                Some(expires_at) if now >= expires_at => {}
+                Some(_) => {
+                    self.schedule_expiry(&id);
+                    continue;
+                }
+                None => continue,
+            }
+            let Some(dialog) = self.take(&id) else {
+                continue;
+            };
+            if dialog.state == DialogState::Confirmed {
+                expired.push((id, dialog));
+            } else {
+                info!(call_id = %id.call_id, duration_secs = dialog.created_at.elapsed().as_secs(), "Erken diyaloğun süresi doldu, diyalog sonlandırılıyor.");
+            }
+        }
+        expired
+    }
+}
+
+/// Süresi dolan diyalogları düzenli aralıklarla temizler. Oturum zamanlayıcısı yenilenmeden
+/// (RFC 4028 §10) veya yapılandırılan etkinlik süresi boyunca istek görülmeden dolan onaylanmış diyaloglar,
+/// her iki tarafa gateway'in kendi BYE'ı gönderilerek sonlandırılır.
+pub async fn run_expiry(dialogs: Dialogs, transactions: Transactions, transport: Arc<TransportLayer>, config: Arc<AppConfig>) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        for shard in dialogs.shards() {
+            expired.extend(shard.lock().await.process_timers(now));
+        }
+        for (id, dialog) in expired {
+            let duration_secs = dialog.created_at.elapsed().as_secs();
+            let reason = if dialog.session_timer.is_some() {
+                warn!(call_id = %id.call_id, duration_secs, "Oturum zamanlayıcısı yenilenmedi, diyalog her iki tarafa BYE gönderilerek sonlandırılıyor.");
+                Some("SIP;cause=408;text=\"Session Timer Expired\"")
+            } else {
+                warn!(call_id = %id.call_id, duration_secs, "Diyalogda uzun süredir etkinlik yok, diyalog her iki tarafa BYE gönderilerek sonlandırılıyor.");
+                Some("SIP;cause=408;text=\"Dialog Inactivity Timeout\"")
+            };
+            send_bye(&id, &dialog, Direction::Outbound, reason, &transactions, &transport, &config).await;
+            send_bye(&id, &dialog, Direction::Inbound, reason, &transactions, &transport, &config).await;
+        }
+    }
+}
+
+/// Gateway'in kendi adına oluşturduğu BYE'ı, yeniden iletimleri yapılacak bir işlemle birlikte
+/// gönderir. `Outbound` BYE operatörün adresine, diyaloğun bağlantısından; `Inbound` BYE diyaloğun
+/// iç servis tarafına gider.
+pub async fn send_bye(
+    id: &DialogId,
+    dialog: &Dialog,
+    direction: Direction,
+    reason: Option<&str>,
+    transactions: &Transactions,
+    transport: &Arc<TransportLayer>,
+    config: &AppConfig,
+) {
+    let (target, connection) = match direction {
+        Direction::Outbound => (dialog.remote_addr, dialog.remote_connection),
+        Direction::Inbound => (dialog.local_addr, None),
+    };
+    let branch = new_branch();
+    let mut bye = build_dialog_request("BYE", id, dialog, direction, target, &branch, config);
+    if let Some(reason) = reason {
+        bye.headers.insert("Reason", reason);
+    }
+    let packet = bye.to_bytes();
+    if let Some(key) = TransactionKey::for_request(&bye) {
+        let info = TransactionInfo::originated(direction, &bye, packet.clone(), branch, target);
+        transactions.lock(&id.call_id).await.insert(key, info);
+    }
+    if let Err(e) = transport.send_on(&packet, target, connection).await {
+        error!(error = %e, target = %target, "Gateway'in BYE isteği gönderilemedi.");
+    }
+}
+
+fn tag_of(msg: &SipMessage, header: &str) -> Option<String> {
+    let value = msg.headers.get(header)?;
+    NameAddr::parse(value)?.tag().map(String::from)
+}
+
+/// UAC tarafında route set, yanıttaki `Record-Route` değerlerinin ters sırasıdır (RFC 3261 §12.1.2).
+fn uac_route_set(response: &SipMessage) -> Vec<String> {
+    let mut route_set: Vec<String> = response
+        .headers
+        .get_all("Record-Route")
+        .into_iter()
+        .flat_map(split_header_list)
+        .map(|route| route.trim().to_string())
+        .collect();
+    route_set.reverse();
+    route_set
+}
+
+/// Contact başlık değerindeki ilk adresin URI'sini döndürür.
+fn contact_uri(value: &str) -> Option<String> {
+    let first = split_header_list(value).into_iter().next()?;
+    NameAddr::parse(first).map(|contact| contact.uri.to_string())
+}
+
+/// `Require: 100rel` taşıyan güvenilir bir geçici yanıtın `RSeq` değerini döndürür (RFC 3262 §7.1).
+pub fn reliable_rseq(msg: &SipMessage) -> Option<u32> {
+    if !has_option(msg, "Require", "100rel") {
+        return None;
+    }
+    msg.headers.get("RSeq")?.trim().parse().ok()
+}
+
+/// `RAck: 776656 1 INVITE` değerini (RSeq, CSeq numarası, metot) olarak ayrıştırır (RFC 3262 §7.2).
+fn rack(msg: &SipMessage) -> Option<(u32, u32, String)> {
+    let mut parts = msg.headers.get("RAck")?.split_whitespace();
+    let rseq = parts.next()?.parse().ok()?;
+    let cseq = parts.next()?.parse().ok()?;
+    let method = parts.next()?.to_ascii_uppercase();
+    Some((rseq, cseq, method))
+}
+
+/// `Session-Expires: 1800;refresher=uac` değerindeki süreyi döndürür (RFC 4028 §4).
+/// Bir günden uzun süreler bir güne indirilir.
+pub fn session_expires(msg: &SipMessage) -> Option<Duration> {
+    let value = msg.headers.get("Session-Expires")?;
+    let seconds = value.split(';').next()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_SESSION_EXPIRES))
+}
+
+/// Seçenek etiketi listesi taşıyan bir başlıkta (`Supported`, `Require`) etiketin olup olmadığını döndürür.
+fn has_option(msg: &SipMessage, header: &str, option: &str) -> bool {
+    msg.headers
+        .get_all(header)
+        .into_iter()
+        .flat_map(split_header_list)
+        .any(|value| value.trim().eq_ignore_ascii_case(option))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARRIER: &str = "192.0.2.10:5060";
+    const SIGNALING: &str = "10.0.0.1:5060";
+
+    /// Diyalog başlatan INVITE; `extra`, Content-Length öncesine eklenen başlıklardır.
+    fn invite(from_tag: &str, extra: &str) -> SipMessage {
+        let packet = format!(
+            "INVITE sip:1000@203.0.113.1 SIP/2.0\r\n\
+Via: SIP/2.0/UDP {CARRIER};branch=z9hG4bK.inv\r\n\
+Max-Forwards: 70\r\n\
+Record-Route: <sip:proxy.carrier.example;lr>\r\n\
+From: <sip:2000@192.0.2.10>;tag={from_tag}\r\n\
+To: <sip:1000@203.0.113.1>\r\n\
+Call-ID: dlg-test\r\n\
+CSeq: 1 INVITE\r\n\
+Contact: <sip:2000@192.0.2.10:5060>\r\n\
+{extra}Content-Length: 0\r\n\r\n"
+        );
+        SipMessage::parse(packet.as_bytes()).unwrap()
+    }
+
+    fn response(status: u16, reason: &str, to_tag: &str, contact: &str, extra: &str) -> SipMessage {
+        let packet = format!(
+            "SIP/2.0 {status} {reason}\r\n\
+Via: SIP/2.0/UDP 203.0.113.1:5060;branch=z9hG4bK.gw\r\n\
+From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+To: <sip:1000@203.0.113.1>;tag={to_tag}\r\n\
+Call-ID: dlg-test\r\n\
+CSeq: 1 INVITE\r\n\
+Contact: <{contact}>\r\n\
+{extra}Content-Length: 0\r\n\r\n"
+        );
+        SipMessage::parse(packet.as_bytes()).unwrap()
+    }
+
+    /// INVITE'ı, `direction` yönünde iletilmiş bir işlem kaydına dönüştürür.
+    fn transaction(msg: &SipMessage, direction: Direction) -> (TransactionKey, TransactionInfo) {
+        let (source, target) = match direction {
+            Direction::Inbound => (Peer::udp(CARRIER.parse().unwrap()), Peer::internal(SIGNALING.parse().unwrap())),
+            Direction::Outbound => (Peer::internal(SIGNALING.parse().unwrap()), Peer::udp(CARRIER.parse().unwrap())),
+        };
+        let key = TransactionKey::for_request(msg).unwrap();
+        let info = TransactionInfo::new(direction, msg, source, msg.to_bytes(), "z9hG4bK.gw".to_string(), target);
+        (key, info)
+    }
+
+    fn id(local_tag: &str, remote_tag: &str) -> DialogId {
+        DialogId { call_id: "dlg-test".to_string(), local_tag: local_tag.to_string(), remote_tag: remote_tag.to_string() }
+    }
+
+    /// Operatörden gelen ve iç servisin 200 OK ile kabul ettiği bir çağrının diyaloğunu kurar.
+    fn confirmed_inbound(store: &mut DialogStore, extra: &str) -> DialogId {
+        let request = invite("a1", extra);
+        let (key, info) = transaction(&request, Direction::Inbound);
+        let ok = response(200, "OK", "b1", "sip:signaling@10.0.0.1:5060", extra);
+        store.on_invite_response(&key, &info, &ok, 200);
+        id("b1", "a1")
+    }
+
+    #[test]
+    fn inbound_invite_creates_early_then_confirmed_dialog() {
+        let mut store = DialogStore::new();
+        let request = invite("a1", "");
+        let (key, info) = transaction(&request, Direction::Inbound);
+
+        store.on_invite_response(&key, &info, &response(180, "Ringing", "b1", "sip:signaling@10.0.0.1:5060", ""), 180);
+        assert_eq!(store.dialogs[&id("b1", "a1")].state, DialogState::Early);
+
+        store.on_invite_response(&key, &info, &response(200, "OK", "b1", "sip:signaling@10.0.0.1:5060", ""), 200);
+        let dialog = &store.dialogs[&id("b1", "a1")];
+        assert_eq!(dialog.state, DialogState::Confirmed);
+        assert_eq!(dialog.remote_addr.addr, CARRIER.parse().unwrap());
+        assert_eq!(dialog.local_addr.addr, SIGNALING.parse().unwrap());
+        assert_eq!(dialog.remote_target.as_deref(), Some("sip:2000@192.0.2.10:5060"));
+        assert_eq!(dialog.local_target.as_deref(), Some("sip:signaling@10.0.0.1:5060"));
+        assert_eq!(dialog.route_set, vec!["<sip:proxy.carrier.example;lr>".to_string()]);
+        assert_eq!(dialog.remote_cseq, 1);
+    }
+
+    #[test]
+    fn non_2xx_final_response_removes_early_dialog() {
+        let mut store = DialogStore::new();
+        let request = invite("a1", "");
+        let (key, info) = transaction(&request, Direction::Inbound);
+        store.on_invite_response(&key, &info, &response(180, "Ringing", "b1", "sip:signaling@10.0.0.1:5060", ""), 180);
+        store.on_invite_response(&key, &info, &response(486, "Busy Here", "b1", "sip:signaling@10.0.0.1:5060", ""), 486);
+        assert!(store.dialogs.is_empty());
+        assert!(store.by_call_id.is_empty());
+    }
+
+    #[test]
+    fn forked_outbound_invite_keeps_first_2xx_and_refuses_the_rest() {
+        let mut store = DialogStore::new();
+        let request = invite("a1", "");
+        let (_, info) = transaction(&request, Direction::Outbound);
+
+        for tag in ["b1", "b2"] {
+            let ringing = response(180, "Ringing", tag, "sip:1000@192.0.2.20", "");
+            assert!(matches!(store.on_outbound_invite_response(&info, &ringing, 180, None), InviteResponseAction::Forward));
+        }
+        assert_eq!(store.by_call_id["dlg-test"].len(), 2);
+
+        let ok = response(200, "OK", "b1", "sip:1000@192.0.2.20", "");
+        assert!(matches!(store.on_outbound_invite_response(&info, &ok, 200, None), InviteResponseAction::Forward));
+        assert_eq!(store.dialogs[&id("a1", "b1")].state, DialogState::Confirmed);
+        assert!(!store.dialogs.contains_key(&id("a1", "b2")), "diğer çatalın erken diyaloğu kapanmalı");
+
+        let late_ok = response(200, "OK", "b2", "sip:1000@192.0.2.21", "");
+        match store.on_outbound_invite_response(&info, &late_ok, 200, None) {
+            InviteResponseAction::RefuseFork { id: refused, send_bye, .. } => {
+                assert_eq!(refused, id("a1", "b2"));
+                assert!(send_bye);
+            }
+            InviteResponseAction::Forward => panic!("fazladan 2xx iç servise iletilmemeli"),
+        }
+        match store.on_outbound_invite_response(&info, &late_ok, 200, None) {
+            InviteResponseAction::RefuseFork { send_bye, .. } => assert!(!send_bye, "yinelenen 2xx için yeniden BYE gönderilmemeli"),
+            InviteResponseAction::Forward => panic!("fazladan 2xx iç servise iletilmemeli"),
+        }
+        assert_eq!(store.dialogs.len(), 1);
+    }
+
+    #[test]
+    fn dialog_is_tied_to_the_carrier_connection() {
+        let mut store = DialogStore::new();
+        let request = invite("a1", "");
+        let (key, mut info) = transaction(&request, Direction::Inbound);
+        info.connection = Some(7);
+        store.on_invite_response(&key, &info, &response(200, "OK", "b1", "sip:signaling@10.0.0.1:5060", ""), 200);
+        let id = id("b1", "a1");
+        assert_eq!(store.dialogs[&id].remote_connection, Some(7));
+
+        // Operatörün yeni bir bağlantıdan gönderdiği diyalog içi istek diyaloğu o bağlantıya taşır;
+        // iç servisin istekleri bağlantıyı değiştirmez.
+        let reinvite = SipMessage::parse(
+            b"INVITE sip:1000@203.0.113.1 SIP/2.0\r\n\
+Via: SIP/2.0/TCP 192.0.2.10:5060;branch=z9hG4bK.re\r\n\
+From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+To: <sip:1000@203.0.113.1>;tag=b1\r\n\
+Call-ID: dlg-test\r\n\
+CSeq: 2 INVITE\r\n\
+Content-Length: 0\r\n\r\n",
+        )
+        .unwrap();
+        let dialog = store.on_request(&id, &reinvite, Direction::Inbound, Some(9)).unwrap();
+        assert_eq!(dialog.remote_connection, Some(9));
+        assert_eq!(dialog.remote_cseq, 2);
+        let dialog = store.on_request(&id, &reinvite, Direction::Outbound, None).unwrap();
+        assert_eq!(dialog.remote_connection, Some(9));
+
+        // Giden çağrıda bağlantı, operatörün yanıtının okunduğu bağlantıdır.
+        let (_, info) = transaction(&invite("c1", ""), Direction::Outbound);
+        let mut ok = response(200, "OK", "d1", "sip:1000@192.0.2.20", "");
+        ok.headers.replace("From", "<sip:2000@192.0.2.10>;tag=c1");
+        store.on_outbound_invite_response(&info, &ok, 200, Some(11));
+        assert_eq!(store.dialogs[&DialogId { call_id: "dlg-test".to_string(), local_tag: "c1".to_string(), remote_tag: "d1".to_string() }].remote_connection, Some(11));
+    }
+
+    #[test]
+    fn bye_removes_dialog_and_call_id_index() {
+        let mut store = DialogStore::new();
+        let id = confirmed_inbound(&mut store, "");
+        assert!(store.remove(&id).is_some());
+        assert!(store.dialogs.is_empty());
+        assert!(store.by_call_id.is_empty());
+        assert!(store.remove(&id).is_none());
+        assert!(store.on_request(&id, &invite("a1", ""), Direction::Inbound, None).is_none());
+    }
+
+    #[test]
+    fn confirmed_dialog_without_session_timer_does_not_expire_by_default() {
+        let mut store = DialogStore::new();
+        let id = confirmed_inbound(&mut store, "");
+        assert!(store.process_timers(Instant::now() + Duration::from_secs(24 * 60 * 60)).is_empty());
+        assert!(store.dialogs.contains_key(&id));
+    }
+
+    #[test]
+    fn confirmed_dialog_expires_after_configured_idle_timeout() {
+        let mut store = DialogStore::with_idle_timeout(Some(Duration::from_secs(60)));
+        let id = confirmed_inbound(&mut store, "");
+        let start = Instant::now();
+        assert!(store.process_timers(start + Duration::from_secs(30)).is_empty());
+        let expired = store.process_timers(start + Duration::from_secs(62));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, id);
+        assert!(store.dialogs.is_empty());
+    }
+
+    #[test]
+    fn unrefreshed_session_timer_expires_dialog() {
+        let mut store = DialogStore::new();
+        let id = confirmed_inbound(&mut store, "Session-Expires: 90;refresher=uac\r\n");
+        let start = Instant::now();
+        // 90 sn'lik aralıkta son an 90 - 30 = 60 sn sonradır (RFC 4028 §10).
+        assert!(store.process_timers(start + Duration::from_secs(50)).is_empty());
+        let expired = store.process_timers(start + Duration::from_secs(62));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, id);
+        assert!(expired[0].1.session_timer.is_some());
+    }
+
+    #[test]
+    fn session_refresh_from_either_side_restarts_the_timer() {
+        let mut store = DialogStore::new();
+        let id = confirmed_inbound(&mut store, "Session-Expires: 90;refresher=uac\r\n");
+        let first_deadline = store.dialogs[&id].session_timer.as_ref().unwrap().deadline();
+
+        // refresher=uac olsa da iç servisin (UAS) gönderdiği UPDATE de oturumu yeniler.
+        let update = SipMessage::parse(
+            b"UPDATE sip:2000@192.0.2.10:5060 SIP/2.0\r\n\
+Via: SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bK.upd\r\n\
+From: <sip:1000@203.0.113.1>;tag=b1\r\n\
+To: <sip:2000@192.0.2.10>;tag=a1\r\n\
+Call-ID: dlg-test\r\n\
+CSeq: 1 UPDATE\r\n\
+Content-Length: 0\r\n\r\n",
+        )
+        .unwrap();
+        let ok = response(200, "OK", "a1", "sip:2000@192.0.2.10:5060", "Session-Expires: 1800;refresher=uas\r\n");
+        store.on_target_refresh_response(&update, &ok, Direction::Outbound);
+        let timer = store.dialogs[&id].session_timer.as_ref().unwrap();
+        assert_eq!(timer.interval, Duration::from_secs(1800));
+        assert!(timer.deadline() > first_deadline);
+        assert!(store.process_timers(Instant::now() + Duration::from_secs(120)).is_empty());
+
+        // Session-Expires taşımayan 2xx oturum zamanlayıcısını kapatır.
+        let ok = response(200, "OK", "a1", "sip:2000@192.0.2.10:5060", "");
+        store.on_target_refresh_response(&update, &ok, Direction::Outbound);
+        assert!(store.dialogs[&id].session_timer.is_none());
+    }
+
+    #[test]
+    fn early_dialog_expires_silently() {
+        let mut store = DialogStore::new();
+        let request = invite("a1", "");
+        let (key, info) = transaction(&request, Direction::Inbound);
+        store.on_invite_response(&key, &info, &response(180, "Ringing", "b1", "sip:signaling@10.0.0.1:5060", ""), 180);
+        let expired = store.process_timers(Instant::now() + EARLY_DIALOG_TIMEOUT + Duration::from_secs(2));
+        assert!(expired.is_empty(), "erken diyaloglar için BYE gönderilmemeli");
+        assert!(store.dialogs.is_empty());
+    }
+}
\ No newline at end of file
@@ -1,203 +1,640 @@
-// sentiric-sip-gateway-service/src/sip/handler.rs
-
-use crate::config::AppConfig;
-use crate::sip::message::SipMessage;
-use crate::sip::message_builder::OutboundRequestBuilder; // YENİ
-use crate::sip::processor::{self, extract_transaction_key};
-use crate::sip::transaction::{TransactionInfo, Transactions};
-use std::net::SocketAddr;
-use std::str::FromStr;
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::net::UdpSocket;
-use tracing::{debug, error, info, instrument, warn, Span};
-
-#[instrument(
-    name = "sip_packet",
-    level = "info",
-    skip_all,
-    fields(
-        source = %remote_addr,
-        call_id = tracing::field::Empty,
-        cseq = tracing::field::Empty,
-        method = tracing::field::Empty,
-        direction = tracing::field::Empty
-    )
-)]
-pub async fn handle_packet(
-    packet_str: &str, 
-    remote_addr: SocketAddr,
-    sock: &Arc<UdpSocket>,
-    transactions: &Transactions,
-    config: &Arc<AppConfig>,
-) {
-    let msg = match SipMessage::parse(packet_str) {
-        Some(m) => m,
-        None => {
-            warn!("Gelen SIP paketi ayrıştırılamadı, atlanıyor.");
-            return;
-        }
-    };
-    
-    if let Some(call_id) = msg.headers.get("Call-ID") {
-        Span::current().record("call_id", call_id);
-    }
-    if let Some(cseq) = msg.headers.get("CSeq") {
-        Span::current().record("cseq", cseq);
-    }
-
-    if msg.start_line.starts_with("SIP/2.0") {
-        Span::current().record("direction", "response");
-        handle_response(packet_str, remote_addr, sock, transactions, config).await;
-    } else {
-        let method = msg.start_line.split_whitespace().next().unwrap_or("UNKNOWN");
-        Span::current().record("method", method);
-        Span::current().record("direction", "request");
-        
-        handle_request(packet_str, &msg, remote_addr, sock, transactions, config).await;
-    }
-}
-
-// --- DEĞİŞİKLİK BURADA: Fonksiyon artık iç/dış istekleri ayırt ediyor ---
-async fn handle_request(
-    packet_str: &str, 
-    msg: &SipMessage,
-    remote_addr: SocketAddr,
-    sock: &Arc<UdpSocket>,
-    transactions: &Transactions,
-    config: &Arc<AppConfig>,
-) {
-    let is_internal_request = match SocketAddr::from_str(&config.target_addr) {
-        Ok(target_socket_addr) => remote_addr == target_socket_addr,
-        Err(_) => false,
-    };
-
-    if is_internal_request {
-        info!("⬅️ Giden istek alındı (internal -> external)");
-        handle_outbound_request(packet_str, sock, transactions, config).await;
-    } else {
-        info!("➡️ Gelen istek alındı (external -> internal)");
-        handle_inbound_request(msg, remote_addr, sock, transactions, config).await;
-    }
-}
-
-// --- YENİ FONKSİYON: İçeriden gelen istekleri işler ---
-async fn handle_outbound_request(
-    packet_str: &str, 
-    sock: &Arc<UdpSocket>,
-    transactions: &Transactions,
-    config: &Arc<AppConfig>,
-) {
-    let (call_id, cseq_method) = match processor::extract_transaction_key(packet_str) {
-        Some((cid, cmethod)) => (cid, cmethod),
-        None => {
-            warn!("İç servisten Call-ID veya CSeq'siz giden istek geldi, atlanıyor.");
-            return;
-        }
-    };
-
-    let tx_key = if cseq_method == "BYE" || cseq_method == "CANCEL" {
-        (call_id.clone(), "INVITE".to_string())
-    } else {
-        (call_id.clone(), cseq_method.clone())
-    };
-
-    let guard = transactions.lock().await;
-    if let Some(invite_tx) = guard.get(&tx_key).cloned() {
-        drop(guard); 
-
-        if let Some(builder) = OutboundRequestBuilder::new(packet_str, &invite_tx, config) {
-            let modified_packet = builder.build();
-            let target_addr = invite_tx.original_client_addr;
-
-            debug!(to = %target_addr, "Modifiye edilmiş giden istek operatöre yönlendiriliyor.");
-            if let Err(e) = sock.send_to(modified_packet.as_bytes(), target_addr).await {
-                error!(error = %e, target = %target_addr, "Giden istek operatöre yönlendirilemedi.");
-            }
-        } else {
-            error!("Giden istek için SipMessage parse edilemedi.");
-        }
-    } else {
-        warn!(call_id = %call_id, method = %cseq_method, "Giden istekle eşleşen aktif INVITE işlemi bulunamadı. İstek atlanıyor.");
-    }
-}
-
-async fn handle_inbound_request(
-    msg: &SipMessage,
-    remote_addr: SocketAddr,
-    sock: &Arc<UdpSocket>,
-    transactions: &Transactions,
-    config: &Arc<AppConfig>,
-) {
-    let method = msg.start_line.split_whitespace().next().unwrap_or_default();
-    
-    if method == "INVITE" {
-        if let Some(call_id) = msg.headers.get("Call-ID") {
-            let guard = transactions.lock().await;
-            if guard.contains_key(&(call_id.clone(), "INVITE".to_string())) {
-                debug!("Yinelenen INVITE isteği, atlanıyor.");
-                return;
-            }
-        }
-    }
-
-    let modified_packet = processor::rewrite_inbound_request(msg, remote_addr, config);
-    
-    if method == "INVITE" {
-        if let (Some(contact), Some(call_id), Some(_cseq)) = (msg.headers.get("Contact"), msg.headers.get("Call-ID"), msg.headers.get("CSeq")) {
-            let record_route = msg.headers.get("Record-Route").cloned();
-            let mut guard = transactions.lock().await;
-            guard.insert(
-                (call_id.clone(), "INVITE".to_string()),
-                TransactionInfo {
-                    original_client_addr: remote_addr,
-                    original_via_headers: msg.via_headers.clone(),
-                    original_contact_header: contact.clone(),
-                    record_route_header: record_route,
-                    created_at: Instant::now(),
-                },
-            );
-        }
-    }
-    
-    debug!(to = %config.target_addr, "Paket sinyal servisine yönlendiriliyor.");
-    if let Err(e) = sock.send_to(modified_packet.as_bytes(), &config.target_addr).await {
-        error!(error = %e, target = %config.target_addr, "Paket sinyal servisine yönlendirilemedi.");
-    }
-}
-
-
-
-async fn handle_response(
-    packet_str: &str,
-    _remote_addr: SocketAddr,
-    sock: &Arc<UdpSocket>,
-    transactions: &Transactions,
-    config: &Arc<AppConfig>,
-) {
-    let response_line = packet_str.lines().next().unwrap_or("");
-    if let Some((call_id, cseq_method)) = extract_transaction_key(packet_str) {
-        Span::current().record("method", &cseq_method as &str);
-        
-        let tx_key = (call_id, cseq_method.clone());
-        let guard = transactions.lock().await;
-        if let Some(tx_info) = guard.get(&tx_key) {
-            let modified_packet = processor::rewrite_outbound_response(packet_str, tx_info, config);
-            let target_addr = tx_info.original_client_addr;
-            drop(guard);
-            if let Err(e) = sock.send_to(modified_packet.as_bytes(), target_addr).await {
-                error!(error = %e, "Yanıt istemciye yönlendirilemedi.");
-            }
-            if cseq_method == "BYE" || cseq_method == "CANCEL" || response_line.contains(" 4") || response_line.contains(" 5") || response_line.contains(" 6") {
-                let mut guard = transactions.lock().await;
-                debug!("İşlem tamamlandı, ilgili kayıtlar siliniyor.");
-                guard.remove(&(tx_key.0.clone(), "INVITE".to_string()));
-                guard.remove(&tx_key);
-            }
-        } else {
-            debug!("İşlem bulunamadı, yanıt yönlendirilemedi (muhtemelen zaman aşımına uğramış bir işlem).");
-        }
-    } else {
-        warn!("Call-ID veya CSeq bulunamayan yanıt paketi geldi, atlanıyor.");
-    }
+// sentiric-sip-gateway-service/src/sip/handler.rs
+
+use crate::config::AppConfig;
+use crate::error::{GatewayError, SipParseError};
+use crate::metrics::{Metrics, METRICS};
+use crate::sip::dialog::{
+    reliable_rseq, send_bye, session_expires, DialogId, Dialogs, InviteResponseAction, MIN_SESSION_EXPIRES,
+};
+use crate::sip::message::SipMessage;
+use crate::sip::message_builder::{build_cancel_for_request, build_dialog_request, OutboundRequestBuilder, ResponseBuilder};
+use crate::sip::processor;
+use crate::sip::transaction::{
+    client_key_for_response, Direction, Outgoing, ResponseAction, TransactionInfo, TransactionKey, TransactionState,
+    Transactions,
+};
+use crate::sip::uri::{format_host, split_header_list, NameAddr, Scheme, SipUri};
+use crate::sip::via::new_branch;
+use crate::transport::{ConnectionId, Leg, Peer, Transport, TransportLayer};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, warn, Span};
+
+#[instrument(
+    name = "sip_packet",
+    level = "info",
+    skip_all,
+    fields(
+        source = %source,
+        leg = ?source.leg,
+        connection = ?connection,
+        call_id = tracing::field::Empty,
+        cseq = tracing::field::Empty,
+        method = tracing::field::Empty,
+        direction = tracing::field::Empty
+    )
+)]
+pub async fn handle_packet(
+    packet: &[u8],
+    source: Peer,
+    connection: Option<ConnectionId>,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    dialogs: &Dialogs,
+    config: &Arc<AppConfig>,
+) -> Result<(), GatewayError> {
+    let msg = match SipMessage::parse(packet) {
+        Ok(m) => m,
+        Err(e) => {
+            Metrics::increment(&METRICS.packets_rejected_malformed);
+            reject_malformed_request(packet, &e, source, transport, config).await;
+            return Err(e.into());
+        }
+    };
+
+    if let Some(call_id) = msg.headers.get("Call-ID") {
+        Span::current().record("call_id", call_id);
+    }
+    if let Some(cseq) = msg.headers.get("CSeq") {
+        Span::current().record("cseq", cseq);
+    }
+
+    if msg.is_response() {
+        Span::current().record("direction", "response");
+        handle_response(&msg, source.leg, connection, transport, transactions, dialogs, config).await;
+    } else {
+        let method = msg.method().unwrap_or("UNKNOWN");
+        Span::current().record("method", method);
+        Span::current().record("direction", "request");
+        
+        handle_request(&msg, source, connection, transport, transactions, dialogs, config).await;
+    }
+    Ok(())
+}
+
+/// Doğrulanamayan bir isteğe, kusuru belirten bir 400 Bad Request yanıtı gönderir.
+/// Başlangıç satırı istek olarak yorumlanamayan paketler, yanıtlar, ACK'ler ve
+/// Via başlığı olmayan (yanıtın yönlendirilemeyeceği) istekler sessizce atılır.
+async fn reject_malformed_request(
+    packet: &[u8],
+    error: &SipParseError,
+    source: Peer,
+    transport: &Arc<TransportLayer>,
+    config: &Arc<AppConfig>,
+) {
+    if matches!(error, SipParseError::BadStartLine(_)) {
+        return;
+    }
+    let request = match SipMessage::parse_unchecked(packet) {
+        Ok(m) if !m.is_response() && m.method() != Some("ACK") => m,
+        _ => return,
+    };
+    let via_headers = processor::client_via_list(&request, source);
+    if via_headers.is_empty() {
+        return;
+    }
+
+    let reason = error.reason_phrase();
+    let target_addr = processor::via_response_target(&via_headers, source);
+    let warn_agent = config.advertised_addr(target_addr).ip().to_string();
+    let response = ResponseBuilder::new(&request, 400, &reason, config)
+        .via_headers(via_headers)
+        .header("Warning", format!("399 {} \"{}\"", format_host(&warn_agent), reason))
+        .build();
+
+    debug!(to = %target_addr, reason = %reason, "Hatalı isteğe 400 Bad Request yanıtı gönderiliyor.");
+    if let Err(e) = transport.send(&response, target_addr).await {
+        error!(error = %e, target = %target_addr, "400 Bad Request yanıtı gönderilemedi.");
+    }
+}
+
+// --- DEĞİŞİKLİK BURADA: Fonksiyon artık iç/dış istekleri ayırt ediyor ---
+async fn handle_request(
+    msg: &SipMessage,
+    source: Peer,
+    connection: Option<ConnectionId>,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    dialogs: &Dialogs,
+    config: &Arc<AppConfig>,
+) {
+    // İç bacak yapılandırılmışsa yön, isteğin geldiği sokete göre belirlenir: iç ağdaki her servis
+    // gateway üzerinden çağrı başlatabilir, dış bacaktan gelen hiçbir istek iç servis sayılmaz.
+    // Tek soketli kurulumda iç servis, sinyal servisinin adresinden tanınır.
+    let is_internal_request = match config.internal_leg {
+        Some(_) => source.leg == Leg::Internal,
+        None => match SocketAddr::from_str(&config.target_addr) {
+            Ok(target_socket_addr) => source.addr == target_socket_addr,
+            Err(_) => false,
+        },
+    };
+
+    if is_internal_request {
+        info!("⬅️ Giden istek alındı (internal -> external)");
+        handle_outbound_request(msg, source, transport, transactions, dialogs, config).await;
+    } else {
+        info!("➡️ Gelen istek alındı (external -> internal)");
+        handle_inbound_request(msg, source, connection, transport, transactions, dialogs, config).await;
+    }
+}
+
+// --- YENİ FONKSİYON: İçeriden gelen istekleri işler ---
+async fn handle_outbound_request(
+    msg: &SipMessage,
+    source: Peer,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    dialogs: &Dialogs,
+    config: &Arc<AppConfig>,
+) {
+    let method = msg.method().unwrap_or_default();
+    let Some(own_key) = TransactionKey::for_request(msg) else {
+        warn!("İç servisten Via veya CSeq'siz giden istek geldi, atlanıyor.");
+        return;
+    };
+    // Diyalog dışı bir INVITE yeni bir giden çağrı başlatır. Hedef adres, işlem kilidi
+    // alınmadan önce çözülür.
+    let dialog_id = DialogId::for_request(msg, Direction::Outbound);
+    let new_call_target = match (&dialog_id, method) {
+        (None, "INVITE") => resolve_request_target(msg, transport).await,
+        _ => None,
+    };
+
+    let mut guard = transactions.lock(msg.call_id()).await;
+    if method == "ACK" && guard.on_ack(&own_key) {
+        // Operatörden gelen 2xx olmayan yanıt, gateway tarafından zaten onaylandı (hop-by-hop).
+        debug!("2xx olmayan yanıta ait iç ACK işlem katmanında yutuldu.");
+        return;
+    }
+    if method != "ACK" && guard.contains_key(&own_key) {
+        let replay = guard.on_request_retransmission(&own_key);
+        drop(guard);
+        replay_last_response(replay, transport).await;
+        return;
+    }
+    if method == "CANCEL" {
+        drop(guard);
+        handle_cancel(msg, own_key, Direction::Outbound, source, transport, transactions, config).await;
+        return;
+    }
+    if session_interval_too_small(msg) {
+        drop(guard);
+        reject_session_interval(msg, source.on_leg(Leg::Internal), transport, config).await;
+        return;
+    }
+    // 2xx'e ait ACK dahil diyalog içi istekler, diyaloğun route set'i ve remote target'ı ile
+    // operatöre iletilir (RFC 3261 §13.2.2.4, §12.2.1.1). Yeni çağrının INVITE'ı, Route ve
+    // Request-URI'si korunarak çözülen adrese gönderilir.
+    let (dialog, target_addr) = match &dialog_id {
+        Some(dialog_id) => {
+            let Some(dialog) = dialogs.lock(msg.call_id()).await.on_request(dialog_id, msg, Direction::Outbound, None) else {
+                warn!(call_id = %dialog_id.call_id, method = %method, "Giden istekle eşleşen aktif diyalog bulunamadı. İstek atlanıyor.");
+                return;
+            };
+            let target_addr = dialog.remote_addr;
+            (Some(dialog), target_addr)
+        }
+        None => match new_call_target {
+            Some(target_addr) => (None, target_addr),
+            None => {
+                warn!(method = %method, "İç servisten hedefi belirlenemeyen diyalog dışı istek geldi, atlanıyor.");
+                return;
+            }
+        },
+    };
+    let branch = new_branch();
+    let modified_packet = OutboundRequestBuilder::new(msg.clone(), dialog.as_ref(), &branch, target_addr, config).build();
+
+    // İç servise yanıtların geri yönlendirilebilmesi ve yeniden iletimlerin yapılabilmesi
+    // için isteğe ait bir işlem çifti açılır. ACK bir işlem başlatmaz.
+    let mut trying = None;
+    if method != "ACK" {
+        let info = TransactionInfo::new(Direction::Outbound, msg, source, modified_packet.clone(), branch, target_addr);
+        if method == "INVITE" {
+            trying = Some(trying_response(msg, &info, config));
+        }
+        guard.insert(own_key.clone(), info);
+        if let Some((response, _)) = &trying {
+            guard.on_server_response(&own_key, 100, response);
+        }
+    }
+    drop(guard);
+    if let (Some(dialog_id), "BYE") = (&dialog_id, method) {
+        dialogs.lock(msg.call_id()).await.remove(dialog_id);
+    }
+    send_trying(trying, transport).await;
+
+    // Diyalog içi istekler, diyaloğun operatör tarafındaki bağlantısından gönderilir.
+    let connection = dialog.as_ref().and_then(|dialog| dialog.remote_connection);
+    debug!(to = %target_addr, "Modifiye edilmiş giden istek operatöre yönlendiriliyor.");
+    if let Err(e) = transport.send_on(&modified_packet, target_addr, connection).await {
+        error!(error = %e, target = %target_addr, "Giden istek operatöre yönlendirilemedi.");
+    }
+}
+
+async fn handle_inbound_request(
+    msg: &SipMessage,
+    source: Peer,
+    connection: Option<ConnectionId>,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    dialogs: &Dialogs,
+    config: &Arc<AppConfig>,
+) {
+    let method = msg.method().unwrap_or_default();
+    let Some(key) = TransactionKey::for_request(msg) else {
+        warn!("Via veya CSeq bulunamayan istek paketi geldi, atlanıyor.");
+        return;
+    };
+
+    {
+        let mut guard = transactions.lock(msg.call_id()).await;
+        if method == "ACK" && guard.on_ack(&key) {
+            // 2xx olmayan nihai yanıtın ACK'i hop-by-hop'tur; iç ağa iletilmez.
+            debug!("2xx olmayan yanıta ait ACK işlem katmanında yutuldu.");
+            return;
+        }
+        if method != "ACK" && guard.contains_key(&key) {
+            let replay = guard.on_request_retransmission(&key);
+            drop(guard);
+            replay_last_response(replay, transport).await;
+            return;
+        }
+    }
+    if method == "CANCEL" {
+        handle_cancel(msg, key, Direction::Inbound, source, transport, transactions, config).await;
+        return;
+    }
+    if session_interval_too_small(msg) {
+        reject_session_interval(msg, source.on_leg(Leg::External), transport, config).await;
+        return;
+    }
+    // 2xx'e ait ACK ve diğer diyalog içi istekler diyaloğu günceller ve diyaloğun iç servis
+    // tarafına gider. Sinyal servisinin adresi diyalog dışı istekler içindir; diyaloğu bilinmeyen
+    // istekler (ör. gateway'in izlemediği abonelik diyalogları) de oraya iletilir, karşılığını
+    // (ör. 481) iç servis verir.
+    let mut acknowledged = None;
+    let mut dialog = None;
+    if let Some(dialog_id) = DialogId::for_request(msg, Direction::Inbound) {
+        let mut dialogs = dialogs.lock(msg.call_id()).await;
+        match dialogs.on_request(&dialog_id, msg, Direction::Inbound, connection) {
+            Some(found) => {
+                dialog = Some(found);
+                match method {
+                    "BYE" => {
+                        dialogs.remove(&dialog_id);
+                    }
+                    "PRACK" => acknowledged = dialogs.on_prack(&dialog_id, msg),
+                    _ => {}
+                }
+            }
+            None => debug!(call_id = %dialog_id.call_id, "Diyalog içi istekle eşleşen diyalog bulunamadı, istek sinyal servisine iletiliyor."),
+        }
+    }
+    let target_addr = match &dialog {
+        Some(dialog) => dialog.local_addr,
+        None => match resolve_signaling_addr(config).await {
+            Some(target_addr) => Peer::internal(target_addr),
+            None => return,
+        },
+    };
+    let branch = new_branch();
+    let modified_packet = processor::rewrite_inbound_request(msg, dialog.as_ref(), &branch, target_addr, config);
+    // PRACK, güvenilir geçici yanıtın yeniden iletimini durdurur; PRACK'in kendisi yine de
+    // yanıtı üreten iç servise iletilir.
+    if let Some((invite_key, rseq)) = acknowledged {
+        if transactions.lock(msg.call_id()).await.on_prack(&invite_key, rseq) {
+            debug!(rseq, "Güvenilir geçici yanıt PRACK ile onaylandı.");
+        }
+    }
+    
+    // ACK dışındaki her istek, yanıtın isteği gönderen tarafa kendi Via listesiyle
+    // dönebilmesi için bir işlem çifti açar. 2xx'e ait ACK bir işlem başlatmaz (RFC 3261 §17).
+    if method != "ACK" {
+        let mut info = TransactionInfo::new(Direction::Inbound, msg, source, modified_packet.clone(), branch, target_addr);
+        info.connection = connection;
+        let trying = (method == "INVITE").then(|| trying_response(msg, &info, config));
+        let mut guard = transactions.lock(msg.call_id()).await;
+        guard.insert(key.clone(), info);
+        if let Some((response, _)) = &trying {
+            guard.on_server_response(&key, 100, response);
+        }
+        drop(guard);
+        send_trying(trying, transport).await;
+    }
+    
+    debug!(to = %target_addr, "Paket sinyal servisine yönlendiriliyor.");
+    if let Err(e) = transport.send(&modified_packet, target_addr).await {
+        error!(error = %e, target = %target_addr, "Paket sinyal servisine yönlendirilemedi.");
+    }
+}
+
+async fn handle_response(
+    msg: &SipMessage,
+    leg: Leg,
+    connection: Option<ConnectionId>,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    dialogs: &Dialogs,
+    config: &Arc<AppConfig>,
+) {
+    let Some(client_key) = client_key_for_response(msg) else {
+        warn!("Via branch'i veya CSeq bulunamayan yanıt paketi geldi, atlanıyor.");
+        return;
+    };
+    Span::current().record("method", &client_key.1 as &str);
+    let status = msg.status_code().unwrap_or_default();
+
+    let mut guard = transactions.lock(msg.call_id()).await;
+    let Some(tx_key) = guard.find_by_client_key(&client_key) else {
+        debug!("İşlem bulunamadı, yanıt yönlendirilemedi (muhtemelen zaman aşımına uğramış bir işlem).");
+        return;
+    };
+    // İki bacaklı kurulumda yanıt, isteğin gönderildiği bacaktan gelmelidir.
+    if config.internal_leg.is_some() && guard.get(&tx_key).is_some_and(|tx_info| tx_info.client.target.leg != leg) {
+        warn!(leg = ?leg, "Yanıt, isteğin gönderildiği bacaktan gelmedi, atlanıyor.");
+        return;
+    }
+
+    let ack = match guard.on_client_response(&tx_key, msg, status) {
+        ResponseAction::Forward => None,
+        ResponseAction::ForwardWithAck(ack) => Some(ack),
+        ResponseAction::Absorb => {
+            debug!(status, "Yinelenen yanıt işlem katmanında yutuldu.");
+            return;
+        }
+        ResponseAction::ResendAck((ack, target)) => {
+            drop(guard);
+            debug!(status, "Yinelenen nihai yanıt için ACK yeniden gönderiliyor.");
+            if let Err(e) = transport.send(&ack, target).await {
+                error!(error = %e, target = %target, "ACK gönderilemedi.");
+            }
+            return;
+        }
+    };
+
+    // Bekletilen bir CANCEL, INVITE'ın ilk geçici yanıtıyla gönderilir (RFC 3261 §9.1).
+    let cancel = guard.start_pending_cancel(&tx_key);
+    let mut reliable = None;
+    let mut refused = None;
+
+    let forward = match guard.get(&tx_key) {
+        // 100 Trying hop-by-hop'tur; istemci işlemini ilerletir ancak iletilmez (RFC 3261 §16.7).
+        _ if status == 100 => {
+            debug!("100 Trying işlem katmanında yutuldu.");
+            None
+        }
+        // İsteği gateway kendisi yanıtladıysa (CANCEL'a 200 OK, zaman aşımında 408/487)
+        // bir sonraki adımın yanıtı iletilmez.
+        Some(tx_info)
+            if matches!(
+                tx_info.server.state,
+                TransactionState::Completed | TransactionState::Confirmed | TransactionState::Terminated
+            ) =>
+        {
+            debug!(status, "İsteğe gateway tarafından zaten yanıt verildi, yanıt iletilmiyor.");
+            None
+        }
+        Some(tx_info) => {
+            let modified_packet = match tx_info.direction {
+                Direction::Inbound => processor::rewrite_outbound_response(msg, tx_info, config),
+                Direction::Outbound => processor::rewrite_inbound_response(msg, tx_info),
+            };
+            let method = tx_info.original_request.method();
+            match tx_info.direction {
+                Direction::Inbound if method == Some("INVITE") => {
+                    dialogs.lock(msg.call_id()).await.on_invite_response(&tx_key, tx_info, msg, status);
+                    // Operatöre karşı UAS kenarı gateway olduğundan güvenilir geçici yanıtlar
+                    // PRACK gelene kadar gateway tarafından yeniden iletilir (RFC 3262 §3).
+                    if (101..200).contains(&status) {
+                        reliable = reliable_rseq(msg);
+                    }
+                }
+                Direction::Inbound if method == Some("UPDATE") && (200..300).contains(&status) => {
+                    dialogs.lock(msg.call_id()).await.on_target_refresh_response(&tx_info.original_request, msg, Direction::Inbound);
+                }
+                Direction::Outbound if method == Some("INVITE") => {
+                    if let InviteResponseAction::RefuseFork { id, dialog, send_bye } =
+                        dialogs.lock(msg.call_id()).await.on_outbound_invite_response(tx_info, msg, status, connection)
+                    {
+                        refused = Some((id, dialog, send_bye));
+                    }
+                }
+                Direction::Outbound if method == Some("UPDATE") && (200..300).contains(&status) => {
+                    dialogs.lock(msg.call_id()).await.on_target_refresh_response(&tx_info.original_request, msg, Direction::Outbound);
+                }
+                _ => {}
+            }
+            refused.is_none().then(|| (modified_packet, processor::response_target(tx_info)))
+        }
+        None => None,
+    };
+    if let Some((packet, _)) = &forward {
+        guard.on_server_response(&tx_key, status, packet);
+        if let Some(rseq) = reliable {
+            guard.on_reliable_provisional(&tx_key, rseq, packet);
+        }
+    }
+    drop(guard);
+
+    if let Some((packet, target)) = forward {
+        if let Err(e) = transport.send(&packet, target).await {
+            error!(error = %e, "Yanıt istemciye yönlendirilemedi.");
+        }
+    }
+    if let Some((ack, target)) = ack {
+        if let Err(e) = transport.send(&ack, target).await {
+            error!(error = %e, target = %target, "ACK gönderilemedi.");
+        }
+    }
+    // İç servise iletilmeyen fazladan 2xx'i gateway kendisi onaylar ve kapatır (RFC 3261 §13.2.2.4).
+    if let Some((id, dialog, bye)) = refused {
+        let ack = build_dialog_request("ACK", &id, &dialog, Direction::Outbound, dialog.remote_addr, &new_branch(), config).to_bytes();
+        if let Err(e) = transport.send_on(&ack, dialog.remote_addr, dialog.remote_connection).await {
+            error!(error = %e, target = %dialog.remote_addr, "Fazladan 2xx için ACK gönderilemedi.");
+        }
+        if bye {
+            send_bye(&id, &dialog, Direction::Outbound, None, transactions, transport, config).await;
+        }
+    }
+    if let Some((cancel, target)) = cancel {
+        debug!(to = %target, "Bekletilen CANCEL bir sonraki adıma gönderiliyor.");
+        if let Err(e) = transport.send(&cancel, target).await {
+            error!(error = %e, target = %target, "CANCEL iletilemedi.");
+        }
+    }
+}
+
+/// CANCEL'ı, aynı branch'i taşıyan INVITE işlemiyle eşleştirir (RFC 3261 §9.2). CANCEL'a hemen
+/// 200 OK verilir; INVITE henüz nihai yanıt almadıysa bir sonraki adıma, INVITE'ın iletildiği
+/// branch ile gateway'in kendi CANCEL'ı gönderilir (§16.10). INVITE'a verilecek 487 Request
+/// Terminated, iptal edilen taraftan gelir ve INVITE işlemi üzerinden iletilir.
+async fn handle_cancel(
+    msg: &SipMessage,
+    key: TransactionKey,
+    direction: Direction,
+    source: Peer,
+    transport: &Arc<TransportLayer>,
+    transactions: &Transactions,
+    config: &Arc<AppConfig>,
+) {
+    let mut guard = transactions.lock(msg.call_id()).await;
+    let Some(invite_tx) = guard.get(&key.with_method("INVITE")) else {
+        drop(guard);
+        debug!("CANCEL ile eşleşen INVITE işlemi bulunamadı, 481 yanıtı gönderiliyor.");
+        send_response(msg, 481, "Call/Transaction Does Not Exist", source.on_leg(direction.source_leg()), transport, config).await;
+        return;
+    };
+    let Some(cancel) = build_cancel_for_request(&invite_tx.client.request, msg.headers.get("Reason")) else {
+        warn!("İletilen INVITE'tan CANCEL oluşturulamadı, CANCEL atlanıyor.");
+        return;
+    };
+    let info = TransactionInfo::new(
+        direction,
+        msg,
+        source,
+        cancel,
+        invite_tx.client.key.0.clone(),
+        invite_tx.client.target,
+    );
+    let ok = ResponseBuilder::new(msg, 200, "OK", config)
+        .via_headers(info.original_via_headers.clone())
+        .build();
+    let ok_target = processor::response_target(&info);
+    let forward = guard.on_cancel(key, info, &ok);
+    drop(guard);
+
+    debug!(to = %ok_target, "CANCEL için 200 OK gönderiliyor.");
+    if let Err(e) = transport.send(&ok, ok_target).await {
+        error!(error = %e, target = %ok_target, "CANCEL için 200 OK gönderilemedi.");
+    }
+    match forward {
+        Some((cancel, target)) => {
+            debug!(to = %target, "CANCEL bir sonraki adıma iletiliyor.");
+            if let Err(e) = transport.send(&cancel, target).await {
+                error!(error = %e, target = %target, "CANCEL iletilemedi.");
+            }
+        }
+        None => debug!("CANCEL, INVITE'ın ilk geçici yanıtına kadar bekletiliyor veya INVITE zaten yanıtlandı."),
+    }
+}
+
+/// Yeniden iletilen bir isteğe, işlemin son yanıtını tekrar gönderir. Henüz yanıt yoksa istek yutulur.
+async fn replay_last_response(replay: Option<Outgoing>, transport: &Arc<TransportLayer>) {
+    match replay {
+        Some((response, target)) => {
+            debug!(to = %target, "Yinelenen istek için son yanıt yeniden gönderiliyor.");
+            if let Err(e) = transport.send(&response, target).await {
+                error!(error = %e, target = %target, "Son yanıt yeniden gönderilemedi.");
+            }
+        }
+        None => debug!("Yinelenen istek (yeniden iletim) işlem katmanında yutuldu."),
+    }
+}
+
+/// Bir isteğe, gateway'in kendisinin ürettiği bir yanıtı gönderir.
+async fn send_response(
+    request: &SipMessage,
+    status: u16,
+    reason: &str,
+    source: Peer,
+    transport: &Arc<TransportLayer>,
+    config: &Arc<AppConfig>,
+) {
+    let via_headers = processor::client_via_list(request, source);
+    let target_addr = processor::via_response_target(&via_headers, source);
+    let response = ResponseBuilder::new(request, status, reason, config)
+        .via_headers(via_headers)
+        .build();
+    if let Err(e) = transport.send(&response, target_addr).await {
+        error!(error = %e, target = %target_addr, status, "Yanıt gönderilemedi.");
+    }
+}
+
+/// INVITE veya UPDATE'teki `Session-Expires`, gateway'in kabul ettiği en kısa süreden kısa mı?
+fn session_interval_too_small(msg: &SipMessage) -> bool {
+    matches!(msg.method(), Some("INVITE" | "UPDATE"))
+        && session_expires(msg).is_some_and(|interval| interval < MIN_SESSION_EXPIRES)
+}
+
+/// Çok kısa oturum süresi isteyen isteği, kabul edilen en kısa süreyi bildiren `Min-SE`
+/// başlığıyla 422 Session Interval Too Small yanıtıyla reddeder (RFC 4028 §8.1).
+async fn reject_session_interval(
+    request: &SipMessage,
+    source: Peer,
+    transport: &Arc<TransportLayer>,
+    config: &Arc<AppConfig>,
+) {
+    let via_headers = processor::client_via_list(request, source);
+    let target_addr = processor::via_response_target(&via_headers, source);
+    let response = ResponseBuilder::new(request, 422, "Session Interval Too Small", config)
+        .via_headers(via_headers)
+        .header("Min-SE", MIN_SESSION_EXPIRES.as_secs().to_string())
+        .build();
+    debug!(to = %target_addr, "Oturum süresi çok kısa, 422 yanıtı gönderiliyor.");
+    if let Err(e) = transport.send(&response, target_addr).await {
+        error!(error = %e, target = %target_addr, "422 yanıtı gönderilemedi.");
+    }
+}
+
+/// INVITE'ı karşılayan 100 Trying yanıtını oluşturur. Yanıt, bir sonraki adımın yanıtı
+/// beklenmeden gönderilir; isteği gönderen tarafın yeniden iletimleri böylece durur (RFC 3261 §16.2, §17.2.1).
+fn trying_response(msg: &SipMessage, info: &TransactionInfo, config: &AppConfig) -> Outgoing {
+    let response = ResponseBuilder::new(msg, 100, "Trying", config)
+        .via_headers(info.original_via_headers.clone())
+        .build();
+    (response, processor::response_target(info))
+}
+
+async fn send_trying(trying: Option<Outgoing>, transport: &Arc<TransportLayer>) {
+    if let Some((response, target)) = trying {
+        debug!(to = %target, "100 Trying gönderiliyor.");
+        if let Err(e) = transport.send(&response, target).await {
+            error!(error = %e, target = %target, "100 Trying gönderilemedi.");
+        }
+    }
+}
+
+/// Diyalog dışı bir isteğin gönderileceği adresi, ilk `Route` başlığından veya yoksa
+/// Request-URI'den çözer (RFC 3261 §16.12, §8.1.2). Taşıma katmanı URI'nin `transport`
+/// parametresinden alınır, yazılmamışsa UDP kullanılır; `sips:` URI'leri her zaman TLS ile
+/// gönderilir (RFC 3261 §26.2.2). Port yazılmamışsa taşımanın varsayılan portu kullanılır;
+/// DNS SRV/NAPTR sorgusu yapılmaz. TLS hedeflerinin sertifikası URI'deki alan adına göre doğrulanır.
+async fn resolve_request_target(msg: &SipMessage, transport_layer: &TransportLayer) -> Option<Peer> {
+    let uri = match msg.headers.get("Route") {
+        Some(route) => NameAddr::parse(split_header_list(route).into_iter().next()?)?.uri,
+        None => SipUri::parse(msg.start_line.split_whitespace().nth(1)?)?,
+    };
+    let transport = if uri.scheme == Scheme::Sips {
+        Transport::Tls
+    } else {
+        uri.params.value("transport").and_then(Transport::from_token).unwrap_or(Transport::Udp)
+    };
+    let port = uri.port.unwrap_or(transport.default_port());
+    let resolved = tokio::net::lookup_host((uri.host.as_str(), port)).await;
+    match resolved {
+        Ok(mut addrs) => {
+            let addr = addrs.next()?;
+            if transport == Transport::Tls && uri.host.parse::<IpAddr>().is_err() {
+                transport_layer.remember_tls_server_name(addr, &uri.host);
+            }
+            Some(Peer::new(addr, transport))
+        }
+        Err(e) => {
+            error!(error = %e, host = %uri.host, "İsteğin hedef adresi çözümlenemedi.");
+            None
+        }
+    }
+}
+
+/// Sinyal servisinin adresini çözer. Adres bir alan adı da olabilir (örn. Docker servis adı).
+pub async fn resolve_signaling_addr(config: &AppConfig) -> Option<SocketAddr> {
+    match tokio::net::lookup_host(&config.target_addr).await {
+        Ok(mut addrs) => addrs.next(),
+        Err(e) => {
+            error!(error = %e, target = %config.target_addr, "Sinyal servisinin adresi çözümlenemedi.");
+            None
+        }
+    }
 }
\ No newline at end of file
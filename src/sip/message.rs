@@ -1,58 +1,589 @@
-// File: src/sip/message.rs
-
-use std::collections::HashMap;
-
-/// SIP mesajının ayrıştırılmış halini temsil eden yapı.
-/// Bu yapı, SIP mesajlarını daha güvenli ve kolay bir şekilde işlememizi sağlar.
-#[derive(Debug, Clone)]
-pub struct SipMessage {
-    pub start_line: String,
-    pub headers: HashMap<String, String>,
-    pub via_headers: Vec<String>, // Birden fazla Via başlığını sırasıyla saklamak için
-    pub body: String,
-}
-
-impl SipMessage {
-    /// Ham metin bir paketten yeni bir SipMessage nesnesi oluşturur.
-    /// Operatörlerden gelen çoklu 'Via' başlıklarını doğru bir şekilde ayrıştırır.
-    pub fn parse(packet_str: &str) -> Option<Self> {
-        let mut lines = packet_str.lines();
-        let start_line = lines.next()?.to_string();
-
-        let mut headers = HashMap::new();
-        let mut via_headers = Vec::new();
-        let mut body_lines = Vec::new();
-        let mut in_body = false;
-
-        for line in lines {
-            if in_body {
-                body_lines.push(line);
-                continue;
-            }
-            if line.is_empty() {
-                in_body = true;
-                continue;
-            }
-            if let Some((key, value)) = line.split_once(':') {
-                let key_trimmed = key.trim();
-                let value_trimmed = value.trim().to_string();
-                let key_lower = key_trimmed.to_lowercase();
-
-                // 'Via' başlıklarını (ve kısa formu 'v') özel olarak ele alıp vektöre ekliyoruz.
-                // Başlığın tamamını ("Via: ...") koruyoruz ki yanıtta aynen geri gönderebilelim.
-                if key_lower == "via" || key_lower == "v" {
-                    via_headers.push(line.to_string());
-                } else {
-                    headers.insert(key_trimmed.to_string(), value_trimmed);
-                }
-            }
-        }
-
-        Some(SipMessage {
-            start_line,
-            headers,
-            via_headers,
-            body: body_lines.join("\r\n"),
-        })
-    }
+// File: src/sip/message.rs
+
+use crate::error::SipParseError;
+use std::borrow::Cow;
+
+/// Tek bir başlık satırı için kabul edilen en büyük boyut (byte).
+const MAX_HEADER_LINE_LEN: usize = 8192;
+
+/// Her SIP mesajında bulunması gereken başlıklar (RFC 3261 §8.1.1).
+const MANDATORY_HEADERS: [&str; 5] = ["Via", "From", "To", "Call-ID", "CSeq"];
+
+/// Kısa (compact) başlık adlarını uzun formlarına eşler (RFC 3261 §7.3.3 ve ilgili RFC'ler).
+/// Eşleşme bulunmazsa adın kendisi döndürülür.
+fn canonical_name(name: &str) -> &str {
+    if name.len() != 1 {
+        return name;
+    }
+    match name.as_bytes()[0].to_ascii_lowercase() {
+        b'i' => "Call-ID",
+        b'f' => "From",
+        b't' => "To",
+        b'm' => "Contact",
+        b'l' => "Content-Length",
+        b'c' => "Content-Type",
+        b'k' => "Supported",
+        b's' => "Subject",
+        b'e' => "Content-Encoding",
+        b'o' => "Event",
+        b'r' => "Refer-To",
+        b'u' => "Allow-Events",
+        b'x' => "Session-Expires",
+        b'b' => "Referred-By",
+        b'v' => "Via",
+        _ => name,
+    }
+}
+
+/// İki başlık adının aynı başlığı gösterip göstermediğini, büyük/küçük harf ve
+/// kısa form farklarını gözetmeden kontrol eder.
+fn names_match(a: &str, b: &str) -> bool {
+    canonical_name(a).eq_ignore_ascii_case(canonical_name(b))
+}
+
+/// SIP başlıklarının, paketteki sırasını koruyan listesi.
+/// Aynı addaki başlıklar (Record-Route, Route, Contact, Via...) tekrar edebilir;
+/// aramalar büyük/küçük harfe duyarsızdır ve kısa formları da kapsar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Headers {
+    entries: Vec<HeaderEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HeaderEntry {
+    name: String,
+    value: String,
+    /// Başlık, geçerli UTF-8 olmayan bir satırdan Latin-1 olarak çözüldü; ağa yazılırken
+    /// aynı byte'lara geri çevrilir.
+    latin1: bool,
+}
+
+impl HeaderEntry {
+    fn new(name: &str, value: String) -> Self {
+        HeaderEntry { name: name.to_string(), value, latin1: false }
+    }
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verilen addaki ilk başlığın değerini döndürür.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| names_match(&entry.name, name))
+            .map(|entry| entry.value.as_str())
+    }
+
+    /// Verilen addaki tüm başlıkların değerlerini paketteki sırasıyla döndürür.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| names_match(&entry.name, name))
+            .map(|entry| entry.value.as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| names_match(&entry.name, name))
+    }
+
+    /// Başlığı listenin sonuna ekler. Aynı addaki mevcut başlıklara dokunmaz.
+    pub fn insert(&mut self, name: &str, value: impl Into<String>) {
+        self.entries.push(HeaderEntry::new(name, value.into()));
+    }
+
+    /// Verilen addaki tüm başlıkları tek bir değerle değiştirir.
+    /// Yeni değer ilk eşleşen başlığın yerine yazılır; başlık yoksa sona eklenir.
+    pub fn replace(&mut self, name: &str, value: impl Into<String>) {
+        self.replace_all(name, vec![value.into()]);
+    }
+
+    /// Verilen addaki tüm başlıkları, sırası korunan bir değer listesiyle değiştirir.
+    /// Yeni değerler ilk eşleşen başlığın konumuna yerleştirilir; başlık yoksa sona eklenir.
+    pub fn replace_all(&mut self, name: &str, values: Vec<String>) {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| names_match(&entry.name, name))
+            .unwrap_or(self.entries.len());
+        self.remove(name);
+        let position = position.min(self.entries.len());
+        let new_entries = values.into_iter().map(|value| HeaderEntry::new(name, value));
+        self.entries.splice(position..position, new_entries);
+    }
+
+    /// Verilen addaki tüm başlıkları siler ve silinen değerleri döndürür.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.entries.retain(|entry| {
+            if names_match(&entry.name, name) {
+                removed.push(entry.value.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Ağdan okunan bir başlık satırını ekler.
+    fn insert_decoded(&mut self, name: &str, value: &str, latin1: bool) {
+        self.entries.push(HeaderEntry { latin1, ..HeaderEntry::new(name, value.to_string()) });
+    }
+
+    /// Katlanmış bir satırın devamını son başlığın değerine ekler.
+    fn append_to_last(&mut self, continuation: &str, latin1: bool) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.value.push(' ');
+            entry.value.push_str(continuation);
+            entry.latin1 |= latin1;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|entry| (entry.name.as_str(), entry.value.as_str()))
+    }
+
+    /// Başlıkları ağa yazılacak biçimde ekler. Latin-1 olarak çözülmüş başlıklar orijinal byte'larıyla yazılır.
+    fn write_to(&self, packet: &mut Vec<u8>) {
+        for entry in &self.entries {
+            for part in [entry.name.as_str(), ": ", entry.value.as_str()] {
+                if entry.latin1 {
+                    encode_latin1(part, packet);
+                } else {
+                    packet.extend_from_slice(part.as_bytes());
+                }
+            }
+            packet.extend_from_slice(b"\r\n");
+        }
+    }
+}
+
+/// SIP mesajının ayrıştırılmış halini temsil eden yapı.
+/// Bu yapı, SIP mesajlarını daha güvenli ve kolay bir şekilde işlememizi sağlar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SipMessage {
+    pub start_line: String,
+    pub headers: Headers,
+    /// Mesaj gövdesi, byte düzeyinde değiştirilmeden saklanır (SDP, multipart, ikili içerik).
+    pub body: Vec<u8>,
+}
+
+impl SipMessage {
+    /// Ham bir paketten (byte dizisi) yeni bir SipMessage nesnesi oluşturur ve doğrular.
+    /// Başlıklar paketteki sırasıyla saklanır; operatörlerden gelen çoklu 'Via',
+    /// 'Record-Route' gibi başlıklar kaybolmaz. Gövde, `Content-Length` değerine
+    /// göre çerçevelenir ve sondaki CRLF'ler dahil olduğu gibi korunur.
+    pub fn parse(packet: &[u8]) -> Result<Self, SipParseError> {
+        let msg = Self::parse_unchecked(packet)?;
+        msg.validate()?;
+        Ok(msg)
+    }
+
+    /// Paketi yalnızca çerçeveler; başlangıç satırı, zorunlu başlıklar ve `Content-Length`
+    /// doğrulanmaz. Hatalı bir isteğe 400 yanıtı üretebilmek için kullanılır.
+    /// Başlık satırları esnek biçimde çözülür; gövde hiçbir zaman metne çevrilmez.
+    pub fn parse_unchecked(packet: &[u8]) -> Result<Self, SipParseError> {
+        let (head_len, body_start) = find_header_end(packet);
+        let head = &packet[..head_len];
+
+        let mut lines = head
+            .split(|&b| b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+        let start_line = decode_header_line(lines.next().unwrap_or_default()).trim().to_string();
+        if start_line.is_empty() {
+            return Err(SipParseError::BadStartLine(start_line));
+        }
+
+        let mut headers = Headers::new();
+        for line in lines {
+            let decoded = decode_header_line(line);
+            let latin1 = matches!(decoded, Cow::Owned(_));
+            // Katlanmış (folded) başlık satırları bir önceki başlığın devamıdır (RFC 3261 §7.3.1).
+            if decoded.starts_with([' ', '\t']) {
+                headers.append_to_last(decoded.trim(), latin1);
+                continue;
+            }
+            if let Some((key, value)) = decoded.split_once(':') {
+                headers.insert_decoded(key.trim(), value.trim(), latin1);
+            }
+        }
+
+        let available = &packet[body_start.min(packet.len())..];
+        let body = match headers.get("Content-Length").and_then(|v| v.parse::<usize>().ok()) {
+            Some(len) if len <= available.len() => available[..len].to_vec(),
+            _ => available.to_vec(),
+        };
+
+        Ok(SipMessage {
+            start_line,
+            headers,
+            body,
+        })
+    }
+
+    /// Başlangıç satırını, başlık boyutlarını, zorunlu başlıkları, CSeq'i ve `Content-Length` değerini doğrular.
+    pub fn validate(&self) -> Result<(), SipParseError> {
+        if !self.start_line_is_well_formed() {
+            return Err(SipParseError::BadStartLine(self.start_line.clone()));
+        }
+
+        // Boyut, çerçeveleme sırasında değil burada denetlenir; böylece çok uzun başlık taşıyan
+        // bir isteğe de `parse_unchecked` ile 400 yanıtı üretilebilir.
+        if let Some(len) = self
+            .headers
+            .iter()
+            .map(|(key, value)| key.len() + 2 + value.len())
+            .find(|&len| len > MAX_HEADER_LINE_LEN)
+        {
+            return Err(SipParseError::OversizedHeader(len));
+        }
+
+        for name in MANDATORY_HEADERS {
+            if !self.headers.contains(name) {
+                return Err(SipParseError::MissingHeader(name));
+            }
+        }
+
+        let cseq = self.headers.get("CSeq").unwrap_or_default();
+        let cseq_method = match cseq.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [number, method] if number.parse::<u32>().is_ok() && is_token(method) => *method,
+            _ => return Err(SipParseError::BadCSeq(cseq.to_string())),
+        };
+        if let Some(method) = self.method() {
+            if method != cseq_method {
+                return Err(SipParseError::BadCSeq(cseq.to_string()));
+            }
+        }
+
+        if let Some(declared) = self.headers.get("Content-Length") {
+            if declared.parse::<usize>().ok() != Some(self.body.len()) {
+                return Err(SipParseError::ContentLengthMismatch {
+                    declared: declared.to_string(),
+                    actual: self.body.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.start_line.starts_with("SIP/2.0")
+    }
+
+    /// İstek mesajlarında metodu döndürür; yanıtlarda `None` döner.
+    pub fn method(&self) -> Option<&str> {
+        if self.is_response() {
+            return None;
+        }
+        self.start_line.split_whitespace().next()
+    }
+
+    /// Yanıt mesajlarında durum kodunu döndürür; isteklerde `None` döner.
+    pub fn status_code(&self) -> Option<u16> {
+        if !self.is_response() {
+            return None;
+        }
+        self.start_line.split_whitespace().nth(1)?.parse().ok()
+    }
+
+    /// `Call-ID` değeri; doğrulanmış mesajlarda her zaman bulunur.
+    pub fn call_id(&self) -> &str {
+        self.headers.get("Call-ID").unwrap_or_default()
+    }
+
+    /// `CSeq` başlığının sıra numarasını ve metodunu döndürür.
+    pub fn cseq(&self) -> Option<(u32, &str)> {
+        let mut parts = self.headers.get("CSeq")?.split_whitespace();
+        let number = parts.next()?.parse().ok()?;
+        let method = parts.next()?;
+        Some((number, method))
+    }
+
+    fn start_line_is_well_formed(&self) -> bool {
+        let parts: Vec<&str> = self.start_line.splitn(3, ' ').collect();
+        if self.is_response() {
+            // SIP/2.0 SP Status-Code SP Reason-Phrase
+            parts.len() >= 2
+                && parts[0] == "SIP/2.0"
+                && parts[1].len() == 3
+                && matches!(parts[1].parse::<u16>(), Ok(100..=699))
+        } else {
+            // Method SP Request-URI SP SIP-Version
+            parts.len() == 3
+                && is_token(parts[0])
+                && !parts[1].is_empty()
+                && parts[2].eq_ignore_ascii_case("SIP/2.0")
+        }
+    }
+
+    /// Mesajı, başlık sırasını koruyarak ağa gönderilecek byte dizisine dönüştürür.
+    /// `Content-Length` her zaman gövdenin gerçek uzunluğuna göre yeniden hesaplanır.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut headers = self.headers.clone();
+        headers.replace("Content-Length", self.body.len().to_string());
+
+        let mut packet = Vec::with_capacity(self.start_line.len() + self.body.len() + 512);
+        packet.extend_from_slice(self.start_line.as_bytes());
+        packet.extend_from_slice(b"\r\n");
+        headers.write_to(&mut packet);
+        packet.extend_from_slice(b"\r\n"); // Başlık ve gövde arası boş satır
+        packet.extend_from_slice(&self.body);
+        packet
+    }
+}
+
+/// RFC 3261 §25.1'deki `token` tanımına uyup uymadığını kontrol eder (metot adları için).
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-.!%*_+`'~".contains(c))
+}
+
+/// Bir başlık satırını metne çevirir. Geçerli UTF-8 olmayan satırlar (örn. ISO-8859-1
+/// kodlanmış görünen adlar) reddedilmez; her byte tek bir karakter olarak (Latin-1) çözülür.
+fn decode_header_line(line: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(line) {
+        Ok(s) => Cow::Borrowed(s),
+        Err(_) => Cow::Owned(line.iter().map(|&b| b as char).collect()),
+    }
+}
+
+/// Latin-1 olarak çözülmüş metni orijinal byte'larına çevirir. Sonradan eklenmiş, Latin-1 ile
+/// yazılamayan karakterler (örn. katlanmış bir satırın UTF-8 devamı) UTF-8 olarak yazılır.
+fn encode_latin1(text: &str, out: &mut Vec<u8>) {
+    for c in text.chars() {
+        match u8::try_from(u32::from(c)) {
+            Ok(byte) => out.push(byte),
+            Err(_) => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+/// Başlık bölümünün sonunu bulur. Dönen değer (başlık bölümünün uzunluğu, gövdenin başladığı konum)
+/// çiftidir. Standart `CRLFCRLF` ayırıcısının yanında, hatalı istemcilerin gönderdiği `LFLF` de kabul edilir.
+fn find_header_end(packet: &[u8]) -> (usize, usize) {
+    let crlf = packet.windows(4).position(|w| w == b"\r\n\r\n").map(|pos| (pos, pos + 4));
+    let lf = packet.windows(2).position(|w| w == b"\n\n").map(|pos| (pos, pos + 2));
+    match (crlf, lf) {
+        (Some(a), Some(b)) => if a.0 <= b.0 { a } else { b },
+        (Some(a), None) => a,
+        (None, Some(b)) => b,
+        (None, None) => (packet.len(), packet.len()),
+    }
+}
+
+/// Paketin başlangıç satırının geçerli UTF-8 olup olmadığını kontrol eder.
+/// Başlık değerleri ve gövde esnek biçimde ele alınsa da başlangıç satırı
+/// çözülemeyen bir paket SIP olarak yorumlanamaz.
+pub fn has_valid_start_line(packet: &[u8]) -> bool {
+    let end = packet.iter().position(|&b| b == b'\n').unwrap_or(packet.len());
+    std::str::from_utf8(&packet[..end]).is_ok()
+}
+
+/// Paketi ayrıştırmadan `Call-ID` (veya kısa formu `i`) başlığının ham değerini bulur.
+/// Paketlerin, ayrıştırılmadan önce çağrıya göre sıralı kuyruklara dağıtılması için kullanılır.
+pub fn raw_call_id(packet: &[u8]) -> Option<&[u8]> {
+    let (header_end, _) = find_header_end(packet);
+    packet[..header_end].split(|&b| b == b'\n').skip(1).find_map(|line| {
+        let colon = line.iter().position(|&b| b == b':')?;
+        let name = line[..colon].trim_ascii();
+        let is_call_id = name.eq_ignore_ascii_case(b"Call-ID") || name.eq_ignore_ascii_case(b"i");
+        is_call_id.then(|| line[colon + 1..].trim_ascii())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::sip::message_builder::ResponseBuilder;
+
+    const LATIN1_INVITE: &[u8] = b"INVITE sip:bob@example.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK1\r\n\
+From: \"Jos\xe9 M\xfcller\" <sip:jose@example.com>;tag=1\r\n\
+To: <sip:bob@example.com>\r\n\
+Call-ID: latin1\r\n\
+CSeq: 1 INVITE\r\n\
+Content-Length: 0\r\n\r\n";
+
+    #[test]
+    fn latin1_header_is_decoded() {
+        let msg = SipMessage::parse(LATIN1_INVITE).unwrap();
+        assert_eq!(msg.headers.get("From"), Some("\"José Müller\" <sip:jose@example.com>;tag=1"));
+    }
+
+    #[test]
+    fn latin1_header_round_trips_byte_for_byte() {
+        let msg = SipMessage::parse(LATIN1_INVITE).unwrap();
+        assert_eq!(msg.to_bytes(), LATIN1_INVITE);
+    }
+
+    #[test]
+    fn utf8_header_round_trips_byte_for_byte() {
+        let packet = "INVITE sip:bob@example.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK1\r\n\
+From: \"José\" <sip:jose@example.com>;tag=1\r\n\
+To: <sip:bob@example.com>\r\n\
+Call-ID: utf8\r\n\
+CSeq: 1 INVITE\r\n\
+Content-Length: 0\r\n\r\n";
+        let msg = SipMessage::parse(packet.as_bytes()).unwrap();
+        assert_eq!(msg.to_bytes(), packet.as_bytes());
+    }
+
+    fn request(headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut packet = format!("INVITE sip:bob@example.com SIP/2.0\r\n{headers}\r\n").into_bytes();
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    const BASIC_HEADERS: &str = "Via: SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK1\r\n\
+From: <sip:alice@example.com>;tag=1\r\n\
+To: <sip:bob@example.com>\r\n\
+Call-ID: headers\r\n\
+CSeq: 1 INVITE\r\n";
+
+    #[test]
+    fn get_all_and_replace_all_keep_header_order() {
+        let mut headers = Headers::new();
+        headers.insert("Via", "v1");
+        headers.insert("Record-Route", "<sip:p1;lr>");
+        headers.insert("Call-ID", "c");
+        headers.insert("Record-Route", "<sip:p2;lr>");
+        headers.insert("v", "v2");
+        assert_eq!(headers.get_all("Record-Route"), ["<sip:p1;lr>", "<sip:p2;lr>"]);
+        assert_eq!(headers.get_all("via"), ["v1", "v2"]);
+
+        headers.replace_all("Record-Route", vec!["<sip:a;lr>".to_string(), "<sip:b;lr>".to_string(), "<sip:c;lr>".to_string()]);
+        let names: Vec<_> = headers.iter().map(|(name, value)| format!("{name}={value}")).collect();
+        assert_eq!(names, ["Via=v1", "Record-Route=<sip:a;lr>", "Record-Route=<sip:b;lr>", "Record-Route=<sip:c;lr>", "Call-ID=c", "v=v2"]);
+
+        headers.replace("Via", "v3");
+        assert_eq!(headers.get_all("Via"), ["v3"]);
+        assert_eq!(headers.iter().next(), Some(("Via", "v3")));
+
+        headers.replace_all("Route", vec!["<sip:r;lr>".to_string()]);
+        assert_eq!(headers.iter().last(), Some(("Route", "<sip:r;lr>")));
+        assert_eq!(headers.remove("Record-Route").len(), 3);
+        assert!(!headers.contains("Record-Route"));
+    }
+
+    #[test]
+    fn compact_header_names_match_long_forms() {
+        let packet = b"INVITE sip:bob@example.com SIP/2.0\r\n\
+v: SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK1\r\n\
+f: <sip:alice@example.com>;tag=1\r\n\
+t: <sip:bob@example.com>\r\n\
+i: compact\r\n\
+CSeq: 1 INVITE\r\n\
+m: <sip:alice@192.0.2.1>\r\n\
+l: 0\r\n\r\n";
+        let msg = SipMessage::parse(packet).unwrap();
+        assert_eq!(msg.headers.get("Via"), Some("SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK1"));
+        assert_eq!(msg.headers.get("FROM"), Some("<sip:alice@example.com>;tag=1"));
+        assert_eq!(msg.headers.get("To"), Some("<sip:bob@example.com>"));
+        assert_eq!(msg.call_id(), "compact");
+        assert_eq!(msg.headers.get("Contact"), Some("<sip:alice@192.0.2.1>"));
+        assert_eq!(msg.headers.get("Content-Length"), Some("0"));
+        assert_eq!(raw_call_id(packet), Some(&b"compact"[..]));
+        // Yeniden hesaplanan Content-Length, kısa adlı başlığın yerine uzun adıyla yazılır.
+        let text = String::from_utf8(msg.to_bytes()).unwrap();
+        assert!(text.ends_with("\r\nm: <sip:alice@192.0.2.1>\r\nContent-Length: 0\r\n\r\n"), "{text}");
+    }
+
+    #[test]
+    fn body_is_framed_by_content_length() {
+        // Content-Length'ten sonra gelen fazladan CRLF gövdeye dahil edilmez.
+        let packet = request(&format!("{BASIC_HEADERS}Content-Length: 4\r\n"), b"v=0\n\r\n");
+        let msg = SipMessage::parse(&packet).unwrap();
+        assert_eq!(msg.body, b"v=0\n");
+
+        // Gövdenin kendisine ait sondaki CRLF korunur.
+        let packet = request(&format!("{BASIC_HEADERS}Content-Length: 5\r\n"), b"v=0\r\n");
+        let msg = SipMessage::parse(&packet).unwrap();
+        assert_eq!(msg.body, b"v=0\r\n");
+        assert_eq!(msg.to_bytes(), packet);
+    }
+
+    #[test]
+    fn binary_body_is_kept_byte_for_byte() {
+        let body = [0x00, 0xff, b'\r', b'\n', b'\r', b'\n', 0x80, 0x0a, 0x0a];
+        let packet = request(&format!("{BASIC_HEADERS}Content-Type: application/octet-stream\r\nContent-Length: {}\r\n", body.len()), &body);
+        let msg = SipMessage::parse(&packet).unwrap();
+        assert_eq!(msg.body, body);
+        assert_eq!(msg.to_bytes(), packet);
+    }
+
+    #[test]
+    fn to_bytes_recomputes_content_length() {
+        let packet = request(&format!("{BASIC_HEADERS}Content-Length: 0\r\n"), b"");
+        let mut msg = SipMessage::parse(&packet).unwrap();
+        msg.body = b"v=0\r\no=- 1 1 IN IP4 192.0.2.1\r\n".to_vec();
+        let reparsed = SipMessage::parse(&msg.to_bytes()).unwrap();
+        assert_eq!(reparsed.headers.get("Content-Length"), Some("31"));
+        assert_eq!(reparsed.body, msg.body);
+
+        // Content-Length taşımayan mesaja da eklenir.
+        let mut msg = SipMessage::parse(&request(BASIC_HEADERS, b"")).unwrap();
+        msg.headers.remove("Content-Length");
+        let reparsed = SipMessage::parse(&msg.to_bytes()).unwrap();
+        assert_eq!(reparsed.headers.get("Content-Length"), Some("0"));
+    }
+
+    #[test]
+    fn parse_errors_map_to_400_reason_phrases() {
+        let oversized = format!("{BASIC_HEADERS}Subject: {}\r\n", "x".repeat(MAX_HEADER_LINE_LEN));
+        let cases = [
+            (
+                request(&BASIC_HEADERS.replace("Call-ID: headers\r\n", ""), b""),
+                SipParseError::MissingHeader("Call-ID"),
+                "Missing Mandatory Header: Call-ID",
+            ),
+            (
+                request(&BASIC_HEADERS.replace("CSeq: 1 INVITE", "CSeq: one INVITE"), b""),
+                SipParseError::BadCSeq("one INVITE".to_string()),
+                "Malformed CSeq Header",
+            ),
+            (
+                request(&BASIC_HEADERS.replace("CSeq: 1 INVITE", "CSeq: 1 BYE"), b""),
+                SipParseError::BadCSeq("1 BYE".to_string()),
+                "Malformed CSeq Header",
+            ),
+            (
+                request(&format!("{BASIC_HEADERS}Content-Length: 10\r\n"), b"v=0\r\n"),
+                SipParseError::ContentLengthMismatch { declared: "10".to_string(), actual: 5 },
+                "Content-Length Mismatch",
+            ),
+            (request(&oversized, b""), SipParseError::OversizedHeader(MAX_HEADER_LINE_LEN + 9), "Header Field Too Large"),
+        ];
+        let config = AppConfig::for_tests();
+        for (packet, expected, reason) in cases {
+            let error = SipMessage::parse(&packet).unwrap_err();
+            assert_eq!(error, expected);
+            assert_eq!(error.reason_phrase(), reason);
+
+            let request = SipMessage::parse_unchecked(&packet).unwrap();
+            let response = ResponseBuilder::new(&request, 400, &error.reason_phrase(), &config).build();
+            assert!(response.starts_with(format!("SIP/2.0 400 {reason}\r\n").as_bytes()), "{expected:?}");
+        }
+
+        let bad_start_line = SipMessage::parse(b"INVITE sip:bob@example.com\r\nCall-ID: x\r\n\r\n").unwrap_err();
+        assert_eq!(bad_start_line, SipParseError::BadStartLine("INVITE sip:bob@example.com".to_string()));
+        assert_eq!(bad_start_line.reason_phrase(), "Malformed Request-Line");
+    }
+
+    #[test]
+    fn rewritten_header_is_written_as_utf8() {
+        let mut msg = SipMessage::parse(LATIN1_INVITE).unwrap();
+        msg.headers.replace("From", "\"José\" <sip:jose@example.com>;tag=1");
+        let bytes = msg.to_bytes();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.contains("From: \"José\" <sip:jose@example.com>;tag=1\r\n"));
+    }
 }
\ No newline at end of file
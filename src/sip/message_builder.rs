@@ -1,73 +1,336 @@
-// File: src/sip/message_builder.rs
-
-use crate::config::AppConfig;
-use crate::sip::message::SipMessage;
-use crate::sip::transaction::TransactionInfo;
-use rand::Rng;
-use tracing::instrument;
-
-/// İç ağdan gelen bir isteği, dış dünyaya gönderilecek formata dönüştüren yapı.
-pub struct OutboundRequestBuilder<'a> {
-    msg: SipMessage,
-    tx_info: &'a TransactionInfo,
-    config: &'a AppConfig,
-}
-
-impl<'a> OutboundRequestBuilder<'a> {
-    pub fn new(
-        packet_str: &str,
-        tx_info: &'a TransactionInfo,
-        config: &'a AppConfig,
-    ) -> Option<Self> {
-        SipMessage::parse(packet_str).map(|msg| Self { msg, tx_info, config })
-    }
-
-    /// `BYE` veya `CANCEL` gibi diyalog içi bir isteği yeniden oluşturur.
-    #[instrument(name="build_outbound_request", skip(self))]
-    pub fn build(mut self) -> String {
-        // 1. Route başlığını ekle (en kritik adım)
-        // Saklanan Record-Route başlığını, Route başlığı olarak ekliyoruz.
-        if let Some(record_route) = &self.tx_info.record_route_header {
-            self.msg.headers.insert("Route".to_string(), record_route.clone());
-        }
-
-        // 2. Via başlığını yeniden yaz
-        self.rewrite_via();
-
-        // 3. Contact başlığını temizle (BYE/CANCEL'da olmamalı)
-        self.msg.headers.remove("Contact");
-
-        // 4. Max-Forwards'ı standart değere ayarla
-        self.msg.headers.insert("Max-Forwards".to_string(), "70".to_string());
-
-        // 5. User-Agent başlığını ekle/güncelle
-        self.msg.headers.insert("User-Agent".to_string(), format!("Sentiric Gateway v{}", self.config.service_version));
-
-        // 6. Content-Length'i sıfırla
-        self.msg.headers.insert("Content-Length".to_string(), "0".to_string());
-
-        // 7. Mesajı yeniden birleştir
-        let mut new_lines = vec![self.msg.start_line];
-        new_lines.extend(self.msg.via_headers); // Zaten tek bir tane olmalı
-        for (key, value) in self.msg.headers {
-            new_lines.push(format!("{}: {}", key, value));
-        }
-        new_lines.push(String::new()); // Boş satır
-
-        new_lines.join("\r\n") + "\r\n"
-    }
-
-    fn rewrite_via(&mut self) {
-        let branch: String = rand::thread_rng()
-            .sample_iter(&rand::distributions::Alphanumeric)
-            .take(16)
-            .map(char::from)
-            .collect();
-        let new_via = format!(
-            "Via: SIP/2.0/UDP {}:{};branch=z9hG4bK.{}",
-            self.config.public_ip, self.config.public_port, branch
-        );
-        // Var olan tek Via'yı bizimkiyle değiştiriyoruz.
-        self.msg.via_headers = vec![new_via];
-    }
+// File: src/sip/message_builder.rs
+
+use crate::config::AppConfig;
+use crate::sip::dialog::{Dialog, DialogId};
+use crate::sip::message::{Headers, SipMessage};
+use crate::sip::processor;
+use crate::sip::transaction::Direction;
+use crate::sip::uri::NameAddr;
+use crate::sip::via::Via;
+use crate::transport::Peer;
+use rand::Rng;
+use tracing::instrument;
+
+/// İç ağdan gelen bir isteği, dış dünyaya gönderilecek formata dönüştüren yapı.
+pub struct OutboundRequestBuilder<'a> {
+    msg: SipMessage,
+    dialog: Option<&'a Dialog>,
+    branch: &'a str,
+    target: Peer,
+    config: &'a AppConfig,
+}
+
+impl<'a> OutboundRequestBuilder<'a> {
+    /// `branch`, isteğin operatör tarafındaki istemci işlemine ait branch değeridir; `target`
+    /// ise isteğin gönderileceği operatör adresidir (Via ve Contact, bu adresin taşıma katmanı ve
+    /// adres ailesine göre yazılır).
+    /// Diyalog dışı istekler (ilk INVITE) için `dialog` verilmez; Route ve Request-URI korunur.
+    pub fn new(
+        msg: SipMessage,
+        dialog: Option<&'a Dialog>,
+        branch: &'a str,
+        target: Peer,
+        config: &'a AppConfig,
+    ) -> Self {
+        Self { msg, dialog, branch, target, config }
+    }
+
+    /// İlk INVITE'ı veya diyalog içi bir isteği (BYE, re-INVITE, UPDATE, INFO, PRACK, NOTIFY, REFER, 2xx ACK) yeniden oluşturur.
+    #[instrument(name="build_outbound_request", skip(self))]
+    pub fn build(mut self) -> Vec<u8> {
+        if let Some(dialog) = self.dialog {
+            // 1. Route başlığını ekle (en kritik adım)
+            // Diyaloğun route set'ini, aynı sırayla Route başlıkları olarak ekliyoruz.
+            if !dialog.route_set.is_empty() {
+                self.msg.headers.replace_all("Route", dialog.route_set.clone());
+            }
+
+            // Request-URI, diyaloğun güncel remote target'ıdır (RFC 3261 §12.2.1.1).
+            if let Some(target) = &dialog.remote_target {
+                if let Some((method, rest)) = self.msg.start_line.split_once(' ') {
+                    if let Some((_, version)) = rest.rsplit_once(' ') {
+                        self.msg.start_line = format!("{} {} {}", method, target, version);
+                    }
+                }
+            }
+        }
+
+        // 2. Via başlığını yeniden yaz
+        self.rewrite_via();
+
+        // 3. Contact taşıyan isteklerde (re-INVITE, UPDATE, NOTIFY, REFER...) Contact, karşı tarafın
+        // sonraki isteklerinin gateway'e gelmesi için kendi public adresimizle yeniden yazılır.
+        if self.msg.headers.contains("Contact") {
+            let contacts = self
+                .msg
+                .headers
+                .get_all("Contact")
+                .into_iter()
+                .map(|value| processor::rewrite_contact(value, self.target, self.config))
+                .collect();
+            self.msg.headers.replace_all("Contact", contacts);
+        }
+
+        // 4. Max-Forwards'ı standart değere ayarla
+        self.msg.headers.replace("Max-Forwards", "70");
+
+        // 5. User-Agent başlığını ekle/güncelle
+        self.msg.headers.replace("User-Agent", format!("Sentiric Gateway v{}", self.config.service_version));
+
+        // 6. Mesajı yeniden birleştir. Gövde (re-INVITE SDP'si, INFO içeriği vb.) korunur,
+        // Content-Length gövdeye göre yeniden hesaplanır.
+        self.msg.to_bytes()
+    }
+
+    fn rewrite_via(&mut self) {
+        let advertised = self.config.advertised_addr(self.target);
+        let new_via = Via::new(self.target.transport.via_token(), &advertised.ip().to_string(), advertised.port(), self.branch);
+        // Var olan tek Via'yı bizimkiyle değiştiriyoruz.
+        self.msg.headers.replace("Via", new_via.to_string());
+    }
+}
+/// Gateway'in bir isteğe kendisinin ürettiği yanıtı (400, 408, 487 vb.) oluşturan yapı.
+/// Via, From, To, Call-ID ve CSeq başlıkları istekten kopyalanır (RFC 3261 §8.2.6.2).
+pub struct ResponseBuilder<'a> {
+    request: &'a SipMessage,
+    status: u16,
+    reason: String,
+    via_headers: Option<Vec<String>>,
+    extra_headers: Vec<(&'static str, String)>,
+    config: &'a AppConfig,
+}
+
+impl<'a> ResponseBuilder<'a> {
+    pub fn new(request: &'a SipMessage, status: u16, reason: &str, config: &'a AppConfig) -> Self {
+        Self {
+            request,
+            status,
+            reason: reason.to_string(),
+            via_headers: None,
+            extra_headers: Vec::new(),
+            config,
+        }
+    }
+
+    /// İstekteki Via listesi yerine kullanılacak listeyi belirler
+    /// (örn. `received`/`rport` işlenmiş istemci Via'ları).
+    pub fn via_headers(mut self, via_headers: Vec<String>) -> Self {
+        self.via_headers = Some(via_headers);
+        self
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name, value.into()));
+        self
+    }
+
+    #[instrument(name="build_response", skip(self), fields(status = self.status))]
+    pub fn build(self) -> Vec<u8> {
+        let mut headers = Headers::new();
+        let via_headers = match self.via_headers {
+            Some(via_headers) => via_headers,
+            None => self.request.headers.get_all("Via").into_iter().map(String::from).collect(),
+        };
+        for via in via_headers {
+            headers.insert("Via", via);
+        }
+        for name in ["From", "To", "Call-ID", "CSeq"] {
+            if let Some(value) = self.request.headers.get(name) {
+                headers.insert(name, value);
+            }
+        }
+        // 100 Trying, istekteki Timestamp değerini geri taşır (RFC 3261 §8.2.6.1).
+        if self.status == 100 {
+            if let Some(timestamp) = self.request.headers.get("Timestamp") {
+                headers.insert("Timestamp", timestamp);
+            }
+        }
+
+        // Nihai yanıtlarda To başlığında etiket yoksa ekliyoruz (RFC 3261 §8.2.6.2).
+        if self.status >= 200 {
+            if let Some(to) = headers.get("To").map(String::from) {
+                if NameAddr::parse(&to).is_none_or(|to| to.tag().is_none()) {
+                    headers.replace("To", format!("{};tag={}", to, new_tag()));
+                }
+            }
+        }
+
+        headers.insert("Server", format!("Sentiric Gateway v{}", self.config.service_version));
+        for (name, value) in self.extra_headers {
+            headers.insert(name, value);
+        }
+
+        SipMessage {
+            start_line: format!("SIP/2.0 {} {}", self.status, self.reason),
+            headers,
+            body: Vec::new(),
+        }
+        .to_bytes()
+    }
+}
+
+/// 2xx olmayan bir nihai yanıt için, iletilen INVITE'tan hop-by-hop ACK oluşturur (RFC 3261 §17.1.1.3).
+/// Request-URI, Call-ID, From, en üstteki Via ve Route başlıkları INVITE'tan, To başlığı yanıttan alınır.
+pub fn build_ack_for_response(invite: &[u8], response: &SipMessage) -> Option<Vec<u8>> {
+    let invite = SipMessage::parse_unchecked(invite).ok()?;
+    let request_uri = invite.start_line.split_whitespace().nth(1)?;
+    let cseq_number = invite.headers.get("CSeq")?.split_whitespace().next()?;
+
+    let mut headers = Headers::new();
+    let top_via = Via::parse_list(invite.headers.get_all("Via")).into_iter().next()?;
+    headers.insert("Via", top_via.to_string());
+    for route in invite.headers.get_all("Route") {
+        headers.insert("Route", route);
+    }
+    headers.insert("Max-Forwards", "70");
+    headers.insert("From", invite.headers.get("From")?);
+    headers.insert("To", response.headers.get("To")?);
+    headers.insert("Call-ID", invite.headers.get("Call-ID")?);
+    headers.insert("CSeq", format!("{} ACK", cseq_number));
+
+    let ack = SipMessage {
+        start_line: format!("ACK {} SIP/2.0", request_uri),
+        headers,
+        body: Vec::new(),
+    };
+    Some(ack.to_bytes())
+}
+
+/// İletilen bir INVITE'ı iptal eden CANCEL isteğini oluşturur (RFC 3261 §9.1).
+/// Request-URI, Call-ID, From, To, CSeq numarası, en üstteki Via ve Route başlıkları INVITE ile aynıdır.
+/// `reason`, iptal edilen taraftan gelen CANCEL'daki `Reason` başlığıdır (RFC 3326).
+pub fn build_cancel_for_request(invite: &[u8], reason: Option<&str>) -> Option<Vec<u8>> {
+    let invite = SipMessage::parse_unchecked(invite).ok()?;
+    let request_uri = invite.start_line.split_whitespace().nth(1)?;
+    let (cseq_number, _) = invite.cseq()?;
+
+    let mut headers = Headers::new();
+    let top_via = Via::parse_list(invite.headers.get_all("Via")).into_iter().next()?;
+    headers.insert("Via", top_via.to_string());
+    for route in invite.headers.get_all("Route") {
+        headers.insert("Route", route);
+    }
+    headers.insert("Max-Forwards", "70");
+    headers.insert("From", invite.headers.get("From")?);
+    headers.insert("To", invite.headers.get("To")?);
+    headers.insert("Call-ID", invite.headers.get("Call-ID")?);
+    headers.insert("CSeq", format!("{} CANCEL", cseq_number));
+    if let Some(reason) = reason {
+        headers.insert("Reason", reason);
+    }
+
+    let cancel = SipMessage {
+        start_line: format!("CANCEL {} SIP/2.0", request_uri),
+        headers,
+        body: Vec::new(),
+    };
+    Some(cancel.to_bytes())
+}
+
+/// Gateway'in bir diyalog içinde kendi adına gönderdiği isteği (oturum zamanlayıcısı BYE'ı,
+/// istenmeyen 2xx'e ACK ve BYE) oluşturur. `direction`, isteğin gönderileceği yöndür: `Outbound`
+/// operatöre (route set ve remote target ile), `Inbound` iç servise. Via, isteğin gönderileceği
+/// `target`'ın taşıma katmanı ve adres ailesine göre yazılır. ACK, onayladığı INVITE'ın CSeq
+/// numarasını, diğer istekler bir sonraki numarayı taşır (RFC 3261 §12.2.1.1, §13.2.2.4).
+/// Ek başlıklar (ör. `Reason`) eklenebilmesi için mesaj olarak döner.
+pub fn build_dialog_request(
+    method: &str,
+    id: &DialogId,
+    dialog: &Dialog,
+    direction: Direction,
+    target: Peer,
+    branch: &str,
+    config: &AppConfig,
+) -> SipMessage {
+    let next = if method == "ACK" { 0 } else { 1 };
+    let (request_uri, from, to, cseq) = match direction {
+        Direction::Outbound => (
+            dialog.remote_target.clone(),
+            &dialog.local_party,
+            &dialog.remote_party,
+            dialog.local_cseq + next,
+        ),
+        Direction::Inbound => (
+            dialog.local_target.clone(),
+            &dialog.remote_party,
+            &dialog.local_party,
+            dialog.remote_cseq + next,
+        ),
+    };
+    let request_uri = request_uri
+        .or_else(|| NameAddr::parse(to).map(|to| to.uri.to_string()))
+        .unwrap_or_default();
+
+    let mut headers = Headers::new();
+    let advertised = config.advertised_addr(target);
+    let via = Via::new(target.transport.via_token(), &advertised.ip().to_string(), advertised.port(), branch);
+    headers.insert("Via", via.to_string());
+    if direction == Direction::Outbound {
+        for route in &dialog.route_set {
+            headers.insert("Route", route.as_str());
+        }
+    }
+    headers.insert("Max-Forwards", "70");
+    headers.insert("From", from.as_str());
+    headers.insert("To", to.as_str());
+    headers.insert("Call-ID", id.call_id.as_str());
+    headers.insert("CSeq", format!("{} {}", cseq, method));
+    headers.insert("User-Agent", format!("Sentiric Gateway v{}", config.service_version));
+
+    SipMessage {
+        start_line: format!("{} {} SIP/2.0", method, request_uri),
+        headers,
+        body: Vec::new(),
+    }
+}
+
+/// From/To başlıkları için yeni ve rastgele bir etiket üretir.
+pub fn new_tag() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outbound(method: &str, contact: Option<&str>) -> SipMessage {
+        let contact = contact.map(|value| format!("Contact: {value}\r\n")).unwrap_or_default();
+        let packet = format!(
+            "{method} sip:2000@192.0.2.10:5060 SIP/2.0\r\n\
+Via: SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bK.int\r\n\
+Max-Forwards: 69\r\n\
+From: <sip:1000@10.0.0.1>;tag=a1\r\n\
+To: <sip:2000@192.0.2.10>;tag=b1\r\n\
+Call-ID: builder-test\r\n\
+CSeq: 2 {method}\r\n\
+{contact}Content-Length: 0\r\n\r\n"
+        );
+        SipMessage::parse(packet.as_bytes()).unwrap()
+    }
+
+    fn build(msg: SipMessage) -> SipMessage {
+        let config = AppConfig::for_tests();
+        let target = Peer::udp("192.0.2.10:5060".parse().unwrap());
+        let packet = OutboundRequestBuilder::new(msg, None, "z9hG4bK.gw", target, &config).build();
+        SipMessage::parse(&packet).unwrap()
+    }
+
+    #[test]
+    fn contact_is_rewritten_for_every_method_that_carries_one() {
+        for method in ["INVITE", "UPDATE", "NOTIFY", "REFER"] {
+            let built = build(outbound(method, Some("<sip:1000@10.0.0.1:5060>;expires=60")));
+            assert_eq!(built.headers.get("Contact"), Some("<sip:1000@203.0.113.1:5060>;expires=60"), "{method}");
+        }
+    }
+
+    #[test]
+    fn request_without_contact_stays_without_contact() {
+        let built = build(outbound("BYE", None));
+        assert!(!built.headers.contains("Contact"));
+        assert_eq!(built.headers.get("Via"), Some("SIP/2.0/UDP 203.0.113.1:5060;branch=z9hG4bK.gw"));
+        assert_eq!(built.headers.get("Max-Forwards"), Some("70"));
+    }
 }
\ No newline at end of file
@@ -1,7 +1,12 @@
-// File: src/sip/mod.rs
-
-pub mod handler;
-pub mod processor;
-pub mod transaction;
-pub mod message;
-pub mod message_builder; // YENİ EKLENDİ
\ No newline at end of file
+// File: src/sip/mod.rs
+
+pub mod dialog;
+pub mod handler;
+pub mod processor;
+pub mod shard;
+pub mod timer;
+pub mod transaction;
+pub mod message;
+pub mod message_builder; // YENİ EKLENDİ
+pub mod uri;
+pub mod via;
\ No newline at end of file
@@ -1,110 +1,159 @@
-// File: src/sip/processor.rs
-
-use crate::config::AppConfig;
-use crate::sip::message::SipMessage;
-use crate::sip::transaction::TransactionInfo;
-use std::net::SocketAddr;
-use tracing::{instrument, warn};
-
-/// Dış dünyadan (operatör) gelen bir isteği, iç ağdaki `signaling-service`'e
-/// iletilecek temiz bir formata dönüştürür.
-/// Bu fonksiyon, dış dünyanın karmaşık `Via` başlıklarını "yutar" ve yerine
-/// iç ağda geçerli olan, sadece gateway'in bilgisini içeren TEK bir `Via` başlığı koyar.
-#[instrument(name="rewrite_inbound", skip_all, fields(original_via_count = msg.via_headers.len()))]
-pub fn rewrite_inbound_request(
-    msg: &SipMessage,
-    remote_addr: SocketAddr,
-    config: &AppConfig,
-) -> String {
-    let mut new_lines = vec![msg.start_line.clone()];
-
-    // Yeni ve tek Via başlığını oluştur.
-    let branch = extract_branch_from_via(&msg.via_headers.first().cloned().unwrap_or_default()).unwrap_or_default();
-    let new_via = format!(
-        "Via: SIP/2.0/UDP {}:{};branch={};rport;received={}",
-        config.public_ip,
-        config.public_port,
-        branch,
-        remote_addr.ip()
-    );
-    new_lines.push(new_via);
-
-    // DİKKAT: Orijinal Via başlıkları iç ağa GÖNDERİLMEZ. Topoloji gizlenir.
-    
-    // Diğer tüm başlıkları ve gövdeyi ekle.
-    for (key, value) in &msg.headers {
-        new_lines.push(format!("{}: {}", key, value));
-    }
-    
-    new_lines.push(String::new()); // Başlık ve gövde arası boş satır
-    new_lines.push(msg.body.clone());
-    
-    new_lines.join("\r\n") + "\r\n"
-}
-
-/// İç ağdaki `signaling-service`'ten gelen bir yanıtı, dış dünyadaki
-/// orijinal istemciye (operatöre) gönderilecek doğru ve RFC uyumlu formata dönüştürür.
-/// Bu fonksiyon, `signaling-service`'ten gelen yanıttaki basit, tek `Via` başlığını
-/// atar ve yerine işlem başladığında kaydettiğimiz orijinal, çoklu `Via` listesini koyar.
-#[instrument(name="rewrite_outbound", skip_all, fields(original_via_count = tx_info.original_via_headers.len()))]
-pub fn rewrite_outbound_response(
-    packet_str: &str,
-    tx_info: &TransactionInfo,
-    config: &AppConfig,
-) -> String {
-    let mut msg = match SipMessage::parse(packet_str) {
-        Some(m) => m,
-        None => return packet_str.to_string(),
-    };
-
-    // Gelen yanıttaki tüm Via'ları atıp, orijinal Via listesini koyuyoruz.
-    msg.via_headers = tx_info.original_via_headers.clone();
-
-    // Contact başlığını kendi public IP'mizle güncelliyoruz.
-    if msg.headers.contains_key("Contact") {
-        let new_contact = format!("<sip:gateway@{}:{}>", config.public_ip, config.public_port);
-        msg.headers.insert("Contact".to_string(), new_contact);
-    }
-
-    // Server başlığını ekle/güncelle
-    msg.headers.insert("Server".to_string(), format!("Sentiric Gateway v{}", config.service_version));
-    
-    // Mesajı yeniden birleştir
-    let mut new_lines = vec![msg.start_line];
-    new_lines.extend(msg.via_headers);
-    for (key, value) in msg.headers {
-        new_lines.push(format!("{}: {}", key, value));
-    }
-    new_lines.push(String::new());
-    new_lines.push(msg.body);
-    
-    new_lines.join("\r\n") + "\r\n"
-}
-
-// --- Yardımcı Fonksiyonlar ---
-
-pub fn extract_header_value(packet: &str, header_name: &str) -> Option<String> {
-    packet
-        .lines()
-        .find(|line| line.trim().to_lowercase().starts_with(&format!("{}:", header_name.to_lowercase())))
-        .and_then(|line| line.split_once(':'))
-        .map(|(_, value)| value.trim().to_string())
-}
-
-pub fn extract_transaction_key(packet: &str) -> Option<(String, String)> {
-    let call_id = extract_header_value(packet, "Call-ID")?;
-    let cseq_line = extract_header_value(packet, "CSeq")?;
-    let cseq_parts: Vec<&str> = cseq_line.split_whitespace().collect();
-    if cseq_parts.len() == 2 {
-        Some((call_id, cseq_parts[1].to_string()))
-    } else {
-        warn!(cseq_line = %cseq_line, "Geçersiz CSeq formatı");
-        None
-    }
-}
-
-fn extract_branch_from_via(via_header: &str) -> Option<String> {
-    via_header.split(';').find(|part| part.trim().starts_with("branch="))
-        .and_then(|part| part.split('=').nth(1))
-        .map(|s| s.to_string())
+// File: src/sip/processor.rs
+
+use crate::config::AppConfig;
+use crate::sip::dialog::Dialog;
+use crate::sip::message::SipMessage;
+use crate::sip::transaction::TransactionInfo;
+use crate::sip::uri::{format_host, split_header_list, NameAddr};
+use crate::sip::via::Via;
+use crate::transport::Peer;
+use tracing::instrument;
+
+/// Dış dünyadan (operatör) gelen bir isteği, iç ağdaki `signaling-service`'e
+/// iletilecek temiz bir formata dönüştürür.
+/// Bu fonksiyon, dış dünyanın karmaşık `Via` başlıklarını "yutar" ve yerine
+/// iç ağda geçerli olan, sadece gateway'in bilgisini içeren TEK bir `Via` başlığı koyar.
+/// `branch`, gateway'in iç ağ tarafındaki istemci işlemine ait branch değeridir; `target` ise
+/// isteğin gönderileceği iç servistir (Via'daki adres, servisin bacağına ve adres ailesine göre seçilir).
+/// Diyalog içi isteklerde `dialog` verilir; Request-URI ve Route, giden yöndeki gibi diyalogdan yeniden oluşturulur.
+#[instrument(name="rewrite_inbound", skip_all, fields(original_via_count = msg.headers.get_all("Via").len()))]
+pub fn rewrite_inbound_request(
+    msg: &SipMessage,
+    dialog: Option<&Dialog>,
+    branch: &str,
+    target: Peer,
+    config: &AppConfig,
+) -> Vec<u8> {
+    let mut new_msg = msg.clone();
+
+    if let Some(dialog) = dialog {
+        // Operatörün Route başlıkları kendi proxy'lerine aittir; iç servise giden tarafta route set yoktur.
+        new_msg.headers.remove("Route");
+
+        // Request-URI, iç servisin güncel Contact'ıdır (RFC 3261 §12.2.1.1).
+        if let Some(local_target) = &dialog.local_target {
+            if let Some((method, rest)) = new_msg.start_line.split_once(' ') {
+                if let Some((_, version)) = rest.rsplit_once(' ') {
+                    new_msg.start_line = format!("{} {} {}", method, local_target, version);
+                }
+            }
+        }
+    }
+
+    // Yeni ve tek Via başlığını oluştur. İç ağa giden istekler UDP ile, iç bacak varsa onun üzerinden gönderilir.
+    let advertised = config.advertised_addr(target);
+    let new_via = Via::new(target.transport.via_token(), &advertised.ip().to_string(), advertised.port(), branch);
+
+    // DİKKAT: Orijinal Via başlıkları iç ağa GÖNDERİLMEZ. Topoloji gizlenir.
+    // Yeni Via, ilk orijinal Via'nın yerine yazılır; diğer başlıkların sırası korunur.
+    new_msg.headers.replace("Via", new_via.to_string());
+
+    // Via değiştiği için Content-Length, gövdeden yeniden hesaplanır.
+    new_msg.to_bytes()
+}
+
+/// İç ağdaki `signaling-service`'ten gelen bir yanıtı, dış dünyadaki
+/// orijinal istemciye (operatöre) gönderilecek doğru ve RFC uyumlu formata dönüştürür.
+/// Bu fonksiyon, `signaling-service`'ten gelen yanıttaki basit, tek `Via` başlığını
+/// atar ve yerine işlem başladığında kaydettiğimiz orijinal, çoklu `Via` listesini koyar.
+#[instrument(name="rewrite_outbound", skip_all, fields(original_via_count = tx_info.original_via_headers.len()))]
+pub fn rewrite_outbound_response(
+    msg: &SipMessage,
+    tx_info: &TransactionInfo,
+    config: &AppConfig,
+) -> Vec<u8> {
+    let mut msg = msg.clone();
+
+    // Gelen yanıttaki tüm Via'ları atıp, orijinal Via listesini aynı konuma koyuyoruz.
+    msg.headers.replace_all("Via", tx_info.original_via_headers.clone());
+
+    // Contact başlığındaki URI'lerin sunucu ve port kısmını kendi public adresimizle güncelliyoruz.
+    // Kullanıcı kısmı, görünen ad ve parametreler korunur.
+    if msg.headers.contains("Contact") {
+        let peer = response_target(tx_info);
+        let contacts = msg.headers.get_all("Contact").into_iter().map(|value| rewrite_contact(value, peer, config)).collect();
+        msg.headers.replace_all("Contact", contacts);
+    }
+
+    // Server başlığını ekle/güncelle
+    msg.headers.replace("Server", format!("Sentiric Gateway v{}", config.service_version));
+
+    msg.to_bytes()
+}
+
+/// Operatörden gelen ve iç ağdan başlatılmış bir isteğe ait yanıtı, `signaling-service`'e
+/// gönderilecek formata dönüştürür. Gateway'in eklediği Via atılır ve iç servisin
+/// orijinal Via listesi geri konur; diğer başlıklar olduğu gibi iletilir.
+#[instrument(name="rewrite_inbound_response", skip_all, fields(original_via_count = tx_info.original_via_headers.len()))]
+pub fn rewrite_inbound_response(
+    msg: &SipMessage,
+    tx_info: &TransactionInfo,
+) -> Vec<u8> {
+    let mut msg = msg.clone();
+    msg.headers.replace_all("Via", tx_info.original_via_headers.clone());
+    msg.to_bytes()
+}
+
+// --- Yardımcı Fonksiyonlar ---
+
+/// Bir Contact başlık değerindeki her adresin yalnızca sunucu ve port kısmını, mesajın gönderileceği
+/// `peer`'e duyurulan adresle değiştirir. Karşı tarafın sonraki istekleri aynı taşıma katmanıyla
+/// göndermesi için `transport` parametresi de güncellenir. Ayrıştırılamayan değerler yerine gateway'in genel Contact'ı yazılır.
+pub fn rewrite_contact(value: &str, peer: Peer, config: &AppConfig) -> String {
+    let transport = peer.transport;
+    let advertised = config.advertised_addr(peer);
+    let port = advertised.port();
+    let public_ip = advertised.ip().to_string();
+    split_header_list(value)
+        .into_iter()
+        .map(|item| match NameAddr::parse(item) {
+            Some(mut contact) => {
+                contact.uri.set_host_port(&public_ip, Some(port));
+                match transport.uri_param() {
+                    Some(param) => contact.uri.params.set("transport", Some(param.to_string())),
+                    None => contact.uri.params.remove("transport"),
+                }
+                contact.to_string()
+            }
+            None => match transport.uri_param() {
+                Some(param) => format!("<sip:gateway@{}:{};transport={}>", format_host(&public_ip), port, param),
+                None => format!("<sip:gateway@{}:{}>", format_host(&public_ip), port),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// İstemcinin Via listesini, en üstteki Via'ya `received`/`rport` bilgisi işlenmiş olarak döndürür
+/// (RFC 3261 §18.2.1, RFC 3581). Bu liste işlemde saklanır ve yanıtlara aynen geri konur.
+/// İsteğin bağlantı yönelimli bir taşımayla geldiği durumlarda (Via'daki taşıma adı ne olursa olsun)
+/// `rport` her zaman doldurulur; yanıt böylece isteğin geldiği bağlantının adresine, yani aynı
+/// bağlantıya gönderilir (RFC 3261 §18.2.2).
+pub fn client_via_list(msg: &SipMessage, source: Peer) -> Vec<String> {
+    let mut vias = Via::parse_list(msg.headers.get_all("Via"));
+    if let Some(top) = vias.first_mut() {
+        if source.transport.is_reliable() && top.rport().is_none() {
+            top.params.set("rport", None);
+        }
+        top.apply_received(source.addr);
+    }
+    vias.iter().map(Via::to_string).collect()
+}
+
+/// Yanıtın gönderileceği adresi, saklanan en üst Via'dan hesaplar (RFC 3581 §4).
+/// Adres Via'dan çıkarılamazsa isteğin geldiği adres kullanılır.
+pub fn response_target(tx_info: &TransactionInfo) -> Peer {
+    via_response_target(&tx_info.original_via_headers, tx_info.original_client.on_leg(tx_info.direction.source_leg()))
+}
+
+/// Bir Via listesinin en üstündeki değere göre yanıt adresini hesaplar; adres hesaplanamazsa
+/// isteğin geldiği adres kullanılır. Yanıt, Via'daki taşıma adından bağımsız olarak isteğin
+/// geldiği taşıma katmanı ve bacak (`source`) üzerinden gönderilir.
+pub fn via_response_target(via_headers: &[String], source: Peer) -> Peer {
+    let addr = via_headers
+        .first()
+        .and_then(|via| Via::parse(via))
+        .and_then(|via| via.response_addr())
+        .unwrap_or(source.addr);
+    Peer { addr, ..source }
 }
\ No newline at end of file
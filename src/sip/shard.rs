@@ -0,0 +1,36 @@
+// File: src/sip/shard.rs
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// İşlem ve diyalog depoları için varsayılan parça sayısı.
+pub const DEFAULT_SHARD_COUNT: usize = 64;
+
+/// Call-ID'ye göre parçalara (shard) ayrılmış bir depo. Bir çağrıya ait tüm mesajlar
+/// (istekler, yanıtlar, CANCEL ve ACK) aynı Call-ID'yi taşıdığından aynı parçaya düşer;
+/// farklı çağrıların paketleri birbirinin kilidini beklemez.
+pub struct Sharded<T> {
+    shards: Box<[Mutex<T>]>,
+    hasher: RandomState,
+}
+
+impl<T> Sharded<T> {
+    pub fn new(count: usize, mut make: impl FnMut() -> T) -> Self {
+        Self {
+            shards: (0..count.max(1)).map(|_| Mutex::new(make())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Call-ID'nin düştüğü parçayı kilitler.
+    pub async fn lock(&self, call_id: &str) -> MutexGuard<'_, T> {
+        let index = self.hasher.hash_one(call_id) as usize % self.shards.len();
+        self.shards[index].lock().await
+    }
+
+    /// Tüm parçalar; zamanlayıcı görevleri parçaları sırayla ve ayrı ayrı kilitler.
+    pub fn shards(&self) -> impl Iterator<Item = &Mutex<T>> {
+        self.shards.iter()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,70 @@
+// File: src/sip/timer.rs
+
+use std::time::{Duration, Instant};
+
+/// Sabit adımlı, karma (hashed) zamanlayıcı çarkı.
+/// Her zamanlayıcı, son tarihinin düştüğü dilime eklenir; çark her adımda yalnızca
+/// o anki dilimi tarar. Dilim sayısından uzun süreli zamanlayıcılar, son tarihleri
+/// gelene kadar dilimde bekler. İptal edilen zamanlayıcılar çarktan silinmez;
+/// tetiklendiklerinde sahipleri tarafından geçersiz sayılarak yok sayılır.
+pub struct TimerWheel<T> {
+    slots: Vec<Vec<(u64, T)>>,
+    tick: Duration,
+    origin: Instant,
+    /// Henüz işlenmemiş ilk adım.
+    current_tick: u64,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(tick: Duration, slot_count: usize) -> Self {
+        Self {
+            slots: (0..slot_count).map(|_| Vec::new()).collect(),
+            tick,
+            origin: Instant::now(),
+            current_tick: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `delay` süre sonra tetiklenecek bir zamanlayıcı kurar.
+    pub fn schedule(&mut self, delay: Duration, item: T) {
+        let elapsed = Instant::now().saturating_duration_since(self.origin) + delay;
+        // Zamanlayıcı hiçbir zaman erken tetiklenmesin diye yukarı yuvarlanır.
+        let deadline = elapsed.as_nanos().div_ceil(self.tick.as_nanos()) as u64;
+        let deadline = deadline.max(self.current_tick);
+        let slot = (deadline % self.slots.len() as u64) as usize;
+        self.slots[slot].push((deadline, item));
+        self.len += 1;
+    }
+
+    /// Çarkı `now` anına kadar ilerletir ve süresi dolan zamanlayıcıları döndürür.
+    pub fn advance(&mut self, now: Instant) -> Vec<T> {
+        let target = (now.saturating_duration_since(self.origin).as_nanos() / self.tick.as_nanos()) as u64;
+        let mut expired = Vec::new();
+        // Çok geride kalındıysa tüm dilimleri bir kez taramak yeterlidir.
+        let steps = (target + 1).saturating_sub(self.current_tick).min(self.slots.len() as u64);
+        for step in 0..steps {
+            let slot = ((self.current_tick + step) % self.slots.len() as u64) as usize;
+            let entries = std::mem::take(&mut self.slots[slot]);
+            for (deadline, item) in entries {
+                if deadline <= target {
+                    expired.push(item);
+                } else {
+                    self.slots[slot].push((deadline, item));
+                }
+            }
+        }
+        self.current_tick = self.current_tick.max(target + 1);
+        self.len -= expired.len();
+        expired
+    }
+}
\ No newline at end of file
@@ -1,46 +1,968 @@
-// File: src/sip/transaction.rs
-
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
-use tracing::{info, debug};
-
-#[derive(Clone, Debug)]
-pub struct TransactionInfo {
-    pub original_client_addr: SocketAddr,
-    pub original_via_headers: Vec<String>, // 'Via' başlıklarının tamamını saklar.
-    #[allow(dead_code)] // Bu alan giden BYE/CANCEL istekleri için saklanıyor.
-    pub original_contact_header: String,
-    #[allow(dead_code)] // Bu alan giden BYE/CANCEL istekleri için saklanıyor.
-    pub record_route_header: Option<String>,
-    pub created_at: Instant,
-}
-
-pub type TransactionKey = (String, String); // (Call-ID, CSeq Method)
-pub type Transactions = Arc<Mutex<HashMap<TransactionKey, TransactionInfo>>>;
-
-pub fn new_transaction_manager() -> Transactions {
-    Arc::new(Mutex::new(HashMap::new()))
-}
-
-pub async fn cleanup_old_transactions(transactions: Transactions) {
-    let mut interval = tokio::time::interval(Duration::from_secs(60));
-    loop {
-        interval.tick().await;
-        let mut guard = transactions.lock().await;
-        let before_count = guard.len();
-        guard.retain(|_key, tx_info| tx_info.created_at.elapsed() < Duration::from_secs(120));
-        let after_count = guard.len();
-        if before_count > after_count {
-            info!(
-                cleaned_count = before_count - after_count,
-                remaining_count = after_count,
-                "Temizlik görevi: Eski işlemler temizlendi."
-            );
-        } else if before_count > 0 {
-             debug!(remaining_count = after_count, "Temizlik görevi çalıştı, eski işlem bulunamadı.");
-        }
-    }
+// File: src/sip/transaction.rs
+
+use crate::config::AppConfig;
+use crate::sip::message::SipMessage;
+use crate::sip::message_builder::{build_ack_for_response, build_cancel_for_request, ResponseBuilder};
+use crate::sip::processor;
+use crate::sip::shard::{Sharded, DEFAULT_SHARD_COUNT};
+use crate::sip::timer::TimerWheel;
+use crate::sip::uri::NameAddr;
+use crate::sip::via::{Via, BRANCH_MAGIC_COOKIE};
+use crate::transport::{ConnectionId, Leg, Peer, TransportLayer};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
+
+/// RFC 3261 §17.1.1.1 zamanlayıcı değerleri (UDP için).
+pub const T1: Duration = Duration::from_millis(500);
+pub const T2: Duration = Duration::from_secs(4);
+pub const T4: Duration = Duration::from_secs(5);
+/// Timer B, F, H, J, L ve M için kullanılan 64*T1 süresi.
+const TIMEOUT_64T1: Duration = Duration::from_secs(32);
+/// Proxy INVITE zamanlayıcısı (RFC 3261 §16.6 adım 11): Proceeding'de kalan INVITE'ın üst sınırı.
+/// RFC 3 dakikadan uzun olmasını ister; her 101-199 yanıtıyla yeniden başlar.
+const TIMER_C: Duration = Duration::from_secs(181);
+/// Zamanlayıcı çarkının adımı ve dilim sayısı.
+const TIMER_TICK: Duration = Duration::from_millis(50);
+const TIMER_SLOTS: usize = 1024;
+/// Sonlanmadan kalan kayıtların kontrol edildiği süre; işlem hâlâ etkinse süre yeniden başlatılır.
+const TRANSACTION_TTL: Duration = Duration::from_secs(120);
+
+/// İşlem durum makinesinin durumları (RFC 3261 §17, RFC 6026 `Accepted`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionState {
+    Calling,
+    Trying,
+    Proceeding,
+    Completed,
+    Confirmed,
+    Accepted,
+    Terminated,
+}
+
+/// İşlem zamanlayıcıları. `Reliable`, PRACK beklenen güvenilir geçici yanıtın yeniden
+/// iletimini (RFC 3262 §3); `Expire`, kaydın bellekten silinme zamanını gösterir.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerKind {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    Reliable,
+    Expire,
+}
+
+/// İsteğin hangi yönde iletildiği.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Operatörden iç ağa (external -> internal).
+    Inbound,
+    /// İç ağdan operatöre (internal -> external).
+    Outbound,
+}
+
+impl Direction {
+    /// İsteği gönderen tarafın bulunduğu bacak; işlemin yanıtları bu bacaktan gönderilir.
+    pub fn source_leg(self) -> Leg {
+        match self {
+            Direction::Inbound => Leg::External,
+            Direction::Outbound => Leg::Internal,
+        }
+    }
+}
+
+/// İsteği gönderen tarafa bakan sunucu işlemi (UAS tarafı).
+#[derive(Clone, Debug)]
+pub struct ServerTransaction {
+    pub is_invite: bool,
+    pub state: TransactionState,
+    /// Karşı tarafa gönderilen son yanıt; Timer G yeniden iletimlerinde kullanılır.
+    pub last_response: Option<Vec<u8>>,
+    pub retransmit_interval: Duration,
+    /// PRACK ile onaylanmamış güvenilir geçici yanıt (`Require: 100rel`).
+    pub reliable_provisional: Option<ReliableProvisional>,
+}
+
+/// Karşı tarafa iletilen ve PRACK gelene kadar yeniden gönderilen güvenilir geçici yanıt.
+#[derive(Clone, Debug)]
+pub struct ReliableProvisional {
+    pub rseq: u32,
+    pub packet: Vec<u8>,
+    pub retransmit_interval: Duration,
+    pub first_sent: Instant,
+}
+
+/// İsteği bir sonraki adıma ileten istemci işlemi (UAC tarafı).
+#[derive(Clone, Debug)]
+pub struct ClientTransaction {
+    /// Yanıtların bu işlemle eşleştirildiği anahtar (gateway'in ürettiği branch ve metot).
+    pub key: ClientKey,
+    pub is_invite: bool,
+    pub state: TransactionState,
+    /// İletilen isteğin kendisi; Timer A/E yeniden iletimlerinde kullanılır.
+    pub request: Vec<u8>,
+    pub target: Peer,
+    pub retransmit_interval: Duration,
+    /// 2xx olmayan nihai yanıta gönderilen ACK; yanıt yinelendiğinde tekrar gönderilir.
+    pub ack: Option<Vec<u8>>,
+    /// Bir sonraki adımdan alınan son geçici yanıt; yinelenen geçici yanıtları ayırt etmek için saklanır.
+    pub last_provisional: Option<SipMessage>,
+    /// INVITE için CANCEL alındı mı? Zaman aşımında 408 yerine 487 üretilir.
+    pub cancelled: bool,
+    /// Timer C'nin dolacağı an. Geçici yanıtlar bu anı ileri alır; zamanlayıcı daha önce tetiklenirse
+    /// kalan süre için yeniden kurulur.
+    pub timer_c_deadline: Option<Instant>,
+}
+
+/// Gateway'in ilettiği bir isteğe ait sunucu ve istemci işlem çiftini tutar.
+#[derive(Clone, Debug)]
+pub struct TransactionInfo {
+    /// Zamanlayıcıların, aynı anahtarla sonradan açılan başka bir işleme uygulanmasını önler.
+    pub id: u64,
+    pub direction: Direction,
+    /// İsteğin geldiği adres, taşıma katmanı ve bacak; yanıtlar bu taşıma katmanıyla gönderilir.
+    pub original_client: Peer,
+    /// İsteğin okunduğu akış bağlantısı; UDP ile gelen ve gateway'in kendi istekleri için `None`.
+    pub connection: Option<ConnectionId>,
+    pub original_via_headers: Vec<String>, // 'Via' başlıklarının değerlerini paketteki sırasıyla saklar.
+    pub original_contact_header: String,
+    pub record_route_headers: Vec<String>, // Tüm 'Record-Route' başlıkları, sırasıyla.
+    /// İsteği gönderen taraftan gelen orijinal istek; gateway'in ürettiği yanıtlar (408) için saklanır.
+    pub original_request: SipMessage,
+    pub server: ServerTransaction,
+    pub client: ClientTransaction,
+    pub created_at: Instant,
+}
+
+impl TransactionInfo {
+    pub fn new(
+        direction: Direction,
+        original_request: &SipMessage,
+        source: Peer,
+        forwarded_request: Vec<u8>,
+        client_branch: String,
+        target: Peer,
+    ) -> Self {
+        let is_invite = original_request.method() == Some("INVITE");
+        TransactionInfo {
+            id: 0,
+            direction,
+            original_client: source,
+            connection: None,
+            original_via_headers: processor::client_via_list(original_request, source),
+            original_contact_header: original_request.headers.get("Contact").unwrap_or_default().to_string(),
+            record_route_headers: original_request.headers.get_all("Record-Route").into_iter().map(String::from).collect(),
+            original_request: original_request.clone(),
+            server: ServerTransaction {
+                is_invite,
+                state: if is_invite { TransactionState::Proceeding } else { TransactionState::Trying },
+                last_response: None,
+                retransmit_interval: T1,
+                reliable_provisional: None,
+            },
+            client: ClientTransaction {
+                key: (client_branch, original_request.method().unwrap_or_default().to_string()),
+                is_invite,
+                state: if is_invite { TransactionState::Calling } else { TransactionState::Trying },
+                request: forwarded_request,
+                target,
+                retransmit_interval: T1,
+                ack: None,
+                last_provisional: None,
+                cancelled: false,
+                timer_c_deadline: None,
+            },
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Gateway'in kendi adına gönderdiği bir istek (örn. oturum zamanlayıcısı BYE'ı) için işlem kaydı.
+    /// Sunucu tarafı olmadığından bu isteğe gelen yanıtlar kimseye iletilmez.
+    pub fn originated(direction: Direction, request: &SipMessage, packet: Vec<u8>, branch: String, target: Peer) -> Self {
+        let mut info = TransactionInfo::new(direction, request, target, packet, branch, target);
+        info.server.state = TransactionState::Terminated;
+        info
+    }
+}
+
+/// Ağa gönderilmesi gereken bir paket ve hedefi.
+pub type Outgoing = (Vec<u8>, Peer);
+
+/// Bir sonraki adımdan gelen yanıtın ne yapılacağını belirtir.
+pub enum ResponseAction {
+    /// Yanıt, isteği gönderen tarafa iletilmeli.
+    Forward,
+    /// İlk 2xx olmayan nihai yanıt: isteği gönderen tarafa iletilmeli ve bir sonraki adıma ACK gönderilmeli.
+    ForwardWithAck(Outgoing),
+    /// Yanıt işlem katmanında yutuldu (yinelenen yanıt).
+    Absorb,
+    /// Yinelenen 2xx olmayan nihai yanıt: ACK yeniden gönderilmeli, yanıt iletilmemeli.
+    ResendAck(Outgoing),
+}
+
+/// Sunucu işlemi anahtarı (RFC 3261 §17.2.3): isteğin en üst Via'sındaki branch ve sent-by
+/// değerleri ile metodu. ACK, onayladığı INVITE işleminin anahtarını üretir.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TransactionKey {
+    pub branch: String,
+    pub sent_by: String,
+    pub method: String,
+}
+
+impl TransactionKey {
+    /// Gelen bir isteğin ait olduğu sunucu işleminin anahtarını hesaplar.
+    pub fn for_request(msg: &SipMessage) -> Option<Self> {
+        let method = msg.method()?;
+        let via = Via::parse_list(msg.headers.get_all("Via")).into_iter().next()?;
+        let branch = match via.branch() {
+            Some(branch) if branch.starts_with(BRANCH_MAGIC_COOKIE) => branch.to_string(),
+            // RFC 2543 istemcileri benzersiz branch üretmez; eşleştirme Call-ID, From etiketi
+            // ve CSeq numarası üzerinden yapılır (§17.2.3 sonundaki eski kurallar).
+            _ => {
+                let from_tag = msg.headers.get("From").and_then(NameAddr::parse);
+                let (cseq, _) = msg.cseq()?;
+                format!(
+                    "rfc2543;{};{};{}",
+                    msg.headers.get("Call-ID")?,
+                    from_tag.as_ref().and_then(NameAddr::tag).unwrap_or_default(),
+                    cseq
+                )
+            }
+        };
+        let method = if method == "ACK" { "INVITE" } else { method };
+        Some(TransactionKey { branch, sent_by: via.sent_by(), method: method.to_string() })
+    }
+
+    /// Aynı branch'e ait başka bir metodun işlem anahtarını döndürür.
+    /// CANCEL, iptal ettiği INVITE ile bu yolla eşleştirilir (RFC 3261 §9.2).
+    pub fn with_method(&self, method: &str) -> Self {
+        TransactionKey { method: method.to_string(), ..self.clone() }
+    }
+}
+
+/// İstemci işlemi anahtarı (RFC 3261 §17.1.3): gateway'in eklediği Via'nın branch değeri
+/// ve CSeq metodu. Branch gateway tarafından üretildiği için sent-by karşılaştırılmaz.
+pub type ClientKey = (String, String);
+
+/// Bir yanıtın ait olduğu istemci işleminin anahtarını hesaplar.
+pub fn client_key_for_response(msg: &SipMessage) -> Option<ClientKey> {
+    let via = Via::parse_list(msg.headers.get_all("Via")).into_iter().next()?;
+    let (_, method) = msg.cseq()?;
+    Some((via.branch()?.to_string(), method.to_string()))
+}
+
+/// İşlem kayıtlarını ve bu kayıtlara ait zamanlayıcıları bir arada tutar.
+/// Her parça (shard) kendi deposuna ve zamanlayıcı çarkına sahiptir.
+pub struct TransactionStore {
+    entries: HashMap<TransactionKey, TransactionInfo>,
+    /// Yanıtların eşleştirildiği istemci işlemi anahtarlarından sunucu işlemi anahtarlarına.
+    client_index: HashMap<ClientKey, TransactionKey>,
+    timers: TimerWheel<(TransactionKey, u64, TimerKind)>,
+    next_id: u64,
+}
+
+pub type Transactions = Arc<Sharded<TransactionStore>>;
+
+pub fn new_transaction_manager() -> Transactions {
+    Arc::new(Sharded::new(DEFAULT_SHARD_COUNT, TransactionStore::new))
+}
+
+impl Default for TransactionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionStore {
+    pub fn new() -> Self {
+        TransactionStore {
+            entries: HashMap::new(),
+            client_index: HashMap::new(),
+            timers: TimerWheel::new(TIMER_TICK, TIMER_SLOTS),
+            next_id: 1,
+        }
+    }
+
+    pub fn get(&self, key: &TransactionKey) -> Option<&TransactionInfo> {
+        self.entries.get(key)
+    }
+
+    pub fn contains_key(&self, key: &TransactionKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Bir yanıtın istemci işlemi anahtarına karşılık gelen sunucu işlemi anahtarını bulur.
+    pub fn find_by_client_key(&self, key: &ClientKey) -> Option<TransactionKey> {
+        self.client_index.get(key).cloned()
+    }
+
+    /// Yeni bir işlem çifti ekler ve başlangıç zamanlayıcılarını kurar
+    /// (INVITE için Timer A/B, diğerleri için Timer E/F). Bağlantı yönelimli taşımalarda istek
+    /// yeniden iletilmediği için Timer A/E kurulmaz. Bekletilen bir CANCEL'ın zamanlayıcıları,
+    /// CANCEL gönderildiğinde kurulur.
+    pub fn insert(&mut self, key: TransactionKey, mut info: TransactionInfo) {
+        info.id = self.next_id;
+        self.next_id += 1;
+        let id = info.id;
+        let retransmit = !info.client.target.transport.is_reliable();
+        self.client_index.insert(info.client.key.clone(), key.clone());
+        if info.client.is_invite {
+            if retransmit {
+                self.schedule(&key, id, TimerKind::A, T1);
+            }
+            self.schedule(&key, id, TimerKind::B, TIMEOUT_64T1);
+        } else if info.client.state == TransactionState::Trying {
+            if retransmit {
+                self.schedule(&key, id, TimerKind::E, T1);
+            }
+            self.schedule(&key, id, TimerKind::F, TIMEOUT_64T1);
+        }
+        self.schedule(&key, id, TimerKind::Expire, TRANSACTION_TTL);
+        self.entries.insert(key, info);
+    }
+
+    fn schedule(&mut self, key: &TransactionKey, id: u64, kind: TimerKind, delay: Duration) {
+        self.timers.schedule(delay, (key.clone(), id, kind));
+    }
+
+    /// Kaydı ve ona ait arama dizinlerini siler.
+    fn remove(&mut self, key: &TransactionKey) {
+        let Some(info) = self.entries.remove(key) else {
+            return;
+        };
+        if self.client_index.get(&info.client.key) == Some(key) {
+            self.client_index.remove(&info.client.key);
+        }
+    }
+
+    /// Bir sonraki adımdan gelen yanıtı istemci işlemine uygular (RFC 3261 §17.1.1.2, §17.1.2.2).
+    pub fn on_client_response(&mut self, key: &TransactionKey, response: &SipMessage, status: u16) -> ResponseAction {
+        use TransactionState::*;
+        let Some(info) = self.entries.get_mut(key) else {
+            return ResponseAction::Absorb;
+        };
+        let id = info.id;
+        let client = &mut info.client;
+        // Bir sonraki adımın yeniden ilettiği geçici yanıtlar bir kez iletilir.
+        if (100..200).contains(&status) {
+            if client.last_provisional.as_ref() == Some(response) {
+                return ResponseAction::Absorb;
+            }
+            client.last_provisional = Some(response.clone());
+        }
+        let mut timers = Vec::new();
+        let action = match (client.is_invite, client.state, status) {
+            // INVITE istemci işlemi
+            (true, Calling | Proceeding, 100..=199) => {
+                if client.state == Calling {
+                    client.state = Proceeding;
+                    client.timer_c_deadline = Some(Instant::now() + TIMER_C);
+                    timers.push((TimerKind::C, TIMER_C));
+                } else if status > 100 && !client.cancelled {
+                    client.timer_c_deadline = Some(Instant::now() + TIMER_C);
+                }
+                ResponseAction::Forward
+            }
+            (true, Calling | Proceeding, 200..=299) => {
+                client.state = Accepted;
+                timers.push((TimerKind::M, TIMEOUT_64T1));
+                ResponseAction::Forward
+            }
+            (true, Calling | Proceeding, _) => {
+                // 2xx olmayan nihai yanıtlar hop-by-hop onaylanır (RFC 3261 §17.1.1.3).
+                client.ack = build_ack_for_response(&client.request, response);
+                client.state = Completed;
+                timers.push((TimerKind::D, TIMEOUT_64T1));
+                match &client.ack {
+                    Some(ack) => ResponseAction::ForwardWithAck((ack.clone(), client.target)),
+                    None => ResponseAction::Forward,
+                }
+            }
+            // Accepted durumunda gelen 2xx yinelemeleri uçtan uca iletilir (RFC 6026 §7.2).
+            (true, Accepted, 200..=299) => ResponseAction::Forward,
+            (true, Completed, 300..=699) => match &client.ack {
+                Some(ack) => ResponseAction::ResendAck((ack.clone(), client.target)),
+                None => ResponseAction::Absorb,
+            },
+            // INVITE olmayan istemci işlemi
+            (false, Trying | Proceeding, 100..=199) => {
+                client.state = Proceeding;
+                ResponseAction::Forward
+            }
+            (false, Trying | Proceeding, _) => {
+                client.state = Completed;
+                timers.push((TimerKind::K, T4));
+                ResponseAction::Forward
+            }
+            _ => ResponseAction::Absorb,
+        };
+        for (kind, delay) in timers {
+            self.schedule(key, id, kind, delay);
+        }
+        action
+    }
+
+    /// İsteği gönderen tarafa iletilen yanıtı sunucu işlemine uygular (RFC 3261 §17.2.1, §17.2.2).
+    pub fn on_server_response(&mut self, key: &TransactionKey, status: u16, packet: &[u8]) {
+        use TransactionState::*;
+        let Some(info) = self.entries.get_mut(key) else {
+            return;
+        };
+        let id = info.id;
+        let reliable = processor::response_target(info).transport.is_reliable();
+        let server = &mut info.server;
+        if matches!(server.state, Completed | Confirmed | Accepted | Terminated) {
+            return;
+        }
+        server.last_response = Some(packet.to_vec());
+        let timers = match (server.is_invite, status) {
+            (_, 100..=199) => {
+                server.state = Proceeding;
+                vec![]
+            }
+            (true, 200..=299) => {
+                server.state = Accepted;
+                vec![(TimerKind::L, TIMEOUT_64T1)]
+            }
+            // Bağlantı yönelimli taşımalarda yanıt yeniden iletilmez; yalnızca ACK beklenir (Timer H).
+            (true, _) if reliable => {
+                server.state = Completed;
+                vec![(TimerKind::H, TIMEOUT_64T1)]
+            }
+            (true, _) => {
+                server.state = Completed;
+                server.retransmit_interval = T1;
+                vec![(TimerKind::G, T1), (TimerKind::H, TIMEOUT_64T1)]
+            }
+            (false, _) => {
+                server.state = Completed;
+                vec![(TimerKind::J, TIMEOUT_64T1)]
+            }
+        };
+        for (kind, delay) in timers {
+            self.schedule(key, id, kind, delay);
+        }
+    }
+
+    /// Operatöre iletilen güvenilir bir geçici yanıtı kaydeder ve PRACK gelene kadar T1'den
+    /// başlayarak iki katına çıkan aralıklarla yeniden iletilmesini sağlar (RFC 3262 §3).
+    /// Aynı işlemde daha yeni bir RSeq ile gelen yanıt öncekinin yerini alır.
+    pub fn on_reliable_provisional(&mut self, key: &TransactionKey, rseq: u32, packet: &[u8]) {
+        let Some(info) = self.entries.get_mut(key) else {
+            return;
+        };
+        if info.server.state != TransactionState::Proceeding {
+            return;
+        }
+        let id = info.id;
+        info.server.reliable_provisional = Some(ReliableProvisional {
+            rseq,
+            packet: packet.to_vec(),
+            retransmit_interval: T1,
+            first_sent: Instant::now(),
+        });
+        self.schedule(key, id, TimerKind::Reliable, T1);
+    }
+
+    /// PRACK'in onayladığı güvenilir geçici yanıtın yeniden iletimini durdurur.
+    /// RSeq bekleyen yanıtınkiyle eşleşmezse `false` döner.
+    pub fn on_prack(&mut self, invite_key: &TransactionKey, rseq: u32) -> bool {
+        let Some(info) = self.entries.get_mut(invite_key) else {
+            return false;
+        };
+        if info.server.reliable_provisional.as_ref().is_some_and(|reliable| reliable.rseq == rseq) {
+            info.server.reliable_provisional = None;
+            return true;
+        }
+        false
+    }
+
+    /// CANCEL'ı iptal ettiği INVITE işlemine uygular (RFC 3261 §9.2, §16.10). CANCEL'ın işlemi,
+    /// `ok_response` (200 OK) verilmiş olarak eklenir. INVITE'a henüz nihai yanıt verilmediyse
+    /// bir sonraki adıma gönderilecek CANCEL döndürülür; INVITE henüz geçici yanıt almadıysa
+    /// CANCEL, ilk geçici yanıta kadar bekletilir (§9.1).
+    pub fn on_cancel(&mut self, cancel_key: TransactionKey, mut cancel: TransactionInfo, ok_response: &[u8]) -> Option<Outgoing> {
+        use TransactionState::*;
+        let invite_key = cancel_key.with_method("INVITE");
+        let mut forward = None;
+        if let Some(invite) = self.entries.get_mut(&invite_key) {
+            let pending = invite.server.state == Proceeding && matches!(invite.client.state, Calling | Proceeding);
+            if pending {
+                invite.client.cancelled = true;
+                let id = invite.id;
+                if invite.client.state == Calling {
+                    cancel.client.state = Calling;
+                } else {
+                    forward = Some((cancel.client.request.clone(), cancel.client.target));
+                }
+                // İptal edilen taraf 487 ile yanıt vermezse 64*T1 sonra gateway kendisi yanıtlar.
+                invite.client.timer_c_deadline = Some(Instant::now() + TIMEOUT_64T1);
+                self.schedule(&invite_key, id, TimerKind::C, TIMEOUT_64T1);
+            } else {
+                cancel.client.state = Terminated;
+            }
+        } else {
+            cancel.client.state = Terminated;
+        }
+        self.insert(cancel_key.clone(), cancel);
+        self.on_server_response(&cancel_key, 200, ok_response);
+        forward
+    }
+
+    /// INVITE istemci işlemi bir yanıt aldığında bekletilen CANCEL'ı işler: ilk geçici yanıtta
+    /// CANCEL gönderilmek üzere döndürülür, nihai yanıtta ise artık gerekmediğinden sonlandırılır.
+    pub fn start_pending_cancel(&mut self, invite_key: &TransactionKey) -> Option<Outgoing> {
+        if invite_key.method != "INVITE" {
+            return None;
+        }
+        let invite_state = self.entries.get(invite_key)?.client.state;
+        let cancel_key = invite_key.with_method("CANCEL");
+        let cancel = self.entries.get_mut(&cancel_key)?;
+        if cancel.client.state != TransactionState::Calling {
+            return None;
+        }
+        if invite_state != TransactionState::Proceeding {
+            cancel.client.state = TransactionState::Terminated;
+            self.remove_if_terminated(&cancel_key);
+            return None;
+        }
+        cancel.client.state = TransactionState::Trying;
+        let id = cancel.id;
+        let outgoing = (cancel.client.request.clone(), cancel.client.target);
+        if !cancel.client.target.transport.is_reliable() {
+            self.schedule(&cancel_key, id, TimerKind::E, T1);
+        }
+        self.schedule(&cancel_key, id, TimerKind::F, TIMEOUT_64T1);
+        Some(outgoing)
+    }
+
+    /// Yeniden iletilen bir isteği sunucu işlemine uygular (RFC 3261 §17.2.1, §17.2.2).
+    /// İşlem Proceeding veya Completed durumundaysa son yanıt yeniden gönderilmek üzere döndürülür.
+    /// Accepted durumunda 2xx'in yeniden iletimi iç servise ait olduğundan istek yalnızca yutulur (RFC 6026 §7.1).
+    pub fn on_request_retransmission(&self, key: &TransactionKey) -> Option<Outgoing> {
+        let info = self.entries.get(key)?;
+        match info.server.state {
+            TransactionState::Proceeding | TransactionState::Completed => info
+                .server
+                .last_response
+                .clone()
+                .map(|response| (response, processor::response_target(info))),
+            _ => None,
+        }
+    }
+
+    /// INVITE sunucu işlemine gelen ACK'i işler. ACK, 2xx olmayan nihai yanıta aitse
+    /// (işlem Completed/Confirmed durumundaysa) işlem katmanında yutulur ve `true` döner.
+    pub fn on_ack(&mut self, key: &TransactionKey) -> bool {
+        let Some(info) = self.entries.get_mut(key) else {
+            return false;
+        };
+        let id = info.id;
+        match info.server.state {
+            TransactionState::Completed => {
+                info.server.state = TransactionState::Confirmed;
+                self.schedule(key, id, TimerKind::I, T4);
+                true
+            }
+            TransactionState::Confirmed => true,
+            _ => false,
+        }
+    }
+
+    /// Süresi dolan zamanlayıcıları işler ve gönderilmesi gereken paketleri döndürür.
+    pub fn process_timers(&mut self, now: Instant, config: &AppConfig) -> Vec<Outgoing> {
+        let mut outgoing = Vec::new();
+        for (key, id, kind) in self.timers.advance(now) {
+            if self.entries.get(&key).is_some_and(|info| info.id == id) {
+                self.fire_timer(&key, id, kind, now, config, &mut outgoing);
+            }
+        }
+        outgoing
+    }
+
+    fn fire_timer(
+        &mut self,
+        key: &TransactionKey,
+        id: u64,
+        kind: TimerKind,
+        now: Instant,
+        config: &AppConfig,
+        outgoing: &mut Vec<Outgoing>,
+    ) {
+        use TransactionState::*;
+        let Some(info) = self.entries.get_mut(key) else {
+            return;
+        };
+        let mut timers = Vec::new();
+        let mut timeout_response = None;
+        let mut timer_c_cancel = None;
+        match (kind, info.client.state, info.server.state) {
+            // İstek yeniden iletimi: INVITE için her seferinde iki katı (Timer A),
+            // diğerleri için en fazla T2'ye kadar iki katı (Timer E).
+            (TimerKind::A, Calling, _) => {
+                outgoing.push((info.client.request.clone(), info.client.target));
+                info.client.retransmit_interval *= 2;
+                timers.push((TimerKind::A, info.client.retransmit_interval));
+            }
+            (TimerKind::E, Trying | Proceeding, _) => {
+                outgoing.push((info.client.request.clone(), info.client.target));
+                info.client.retransmit_interval = if info.client.state == Trying {
+                    (info.client.retransmit_interval * 2).min(T2)
+                } else {
+                    T2
+                };
+                timers.push((TimerKind::E, info.client.retransmit_interval));
+            }
+            // Süresi bir geçici yanıtla ileri alınmış Timer C, kalan süre için yeniden kurulur.
+            (TimerKind::C, Proceeding, _) if info.client.timer_c_deadline.is_some_and(|deadline| deadline > now) => {
+                let deadline = info.client.timer_c_deadline.unwrap_or(now);
+                timers.push((TimerKind::C, deadline - now));
+            }
+            // Timer C doldu (RFC 3261 §16.8): geçici yanıt almış INVITE bir sonraki adımda CANCEL ile
+            // iptal edilir. İptal edilen tarafın 487'si isteği gönderen tarafa iletilir; o da gelmezse
+            // 64*T1 sonra gateway kendisi yanıt üretir.
+            (TimerKind::C, Proceeding, _) if !info.client.cancelled => {
+                warn!(branch = %key.branch, "INVITE için nihai yanıt alınamadı (Timer C), bir sonraki adıma CANCEL gönderiliyor.");
+                info.client.cancelled = true;
+                info.client.timer_c_deadline = Some(now + TIMEOUT_64T1);
+                timers.push((TimerKind::C, TIMEOUT_64T1));
+                timer_c_cancel = build_cancel_for_request(&info.client.request, None).and_then(|packet| {
+                    let request = SipMessage::parse_unchecked(&packet).ok()?;
+                    Some(TransactionInfo::originated(info.direction, &request, packet, info.client.key.0.clone(), info.client.target))
+                });
+            }
+            // INVITE olmayan istekte zaman aşımı: yanıt üretilmez, işlem sonlandırılır (RFC 4320 §4.2).
+            // İsteği gönderen tarafın kendi Timer F'si dolar; geç gelen 408'ler yalnızca gereksiz trafiktir.
+            (TimerKind::F, Trying | Proceeding, _) => {
+                warn!(branch = %key.branch, method = %key.method, "INVITE olmayan istek için nihai yanıt alınamadı (Timer F), işlem yanıt üretilmeden sonlandırılıyor.");
+                info.client.state = Terminated;
+                info.server.state = Terminated;
+            }
+            // INVITE zaman aşımı: isteği gönderen tarafa 408 üretilir. CANCEL edilmiş INVITE için
+            // iptal edilen taraf yanıt vermediğinden 487 üretilir.
+            (TimerKind::B, Calling, _) | (TimerKind::C, Proceeding, _) => {
+                let (status, reason) = if info.client.cancelled {
+                    (487, "Request Terminated")
+                } else {
+                    (408, "Request Timeout")
+                };
+                warn!(branch = %key.branch, method = %key.method, timer = ?kind, status, "İşlem zaman aşımına uğradı, yanıt üretiliyor.");
+                info.client.state = Terminated;
+                if matches!(info.server.state, Trying | Proceeding) {
+                    let response = ResponseBuilder::new(&info.original_request, status, reason, config)
+                        .via_headers(info.original_via_headers.clone())
+                        .build();
+                    outgoing.push((response.clone(), processor::response_target(info)));
+                    timeout_response = Some((status, response));
+                }
+            }
+            // 2xx olmayan nihai yanıtın yeniden iletimi (Timer G), ACK gelene kadar.
+            (TimerKind::G, _, Completed) if info.server.is_invite => {
+                if let Some(response) = info.server.last_response.clone() {
+                    outgoing.push((response, processor::response_target(info)));
+                }
+                info.server.retransmit_interval = (info.server.retransmit_interval * 2).min(T2);
+                timers.push((TimerKind::G, info.server.retransmit_interval));
+            }
+            // Güvenilir geçici yanıtın yeniden iletimi; 64*T1 içinde PRACK gelmezse bırakılır.
+            (TimerKind::Reliable, _, Proceeding) => {
+                let target = processor::response_target(info);
+                let Some(reliable) = info.server.reliable_provisional.as_mut() else {
+                    return;
+                };
+                if reliable.first_sent.elapsed() >= TIMEOUT_64T1 {
+                    warn!(branch = %key.branch, rseq = reliable.rseq, "Güvenilir geçici yanıt için PRACK alınamadı, yeniden iletim durduruluyor.");
+                    info.server.reliable_provisional = None;
+                } else {
+                    outgoing.push((reliable.packet.clone(), target));
+                    reliable.retransmit_interval *= 2;
+                    timers.push((TimerKind::Reliable, reliable.retransmit_interval));
+                }
+            }
+            (TimerKind::H, _, Completed) => {
+                warn!(branch = %key.branch, "Nihai yanıt için ACK alınamadı (Timer H), sunucu işlemi sonlandırılıyor.");
+                info.server.state = Terminated;
+            }
+            (TimerKind::I, _, Confirmed) | (TimerKind::J, _, Completed) | (TimerKind::L, _, Accepted) => {
+                info.server.state = Terminated;
+            }
+            (TimerKind::D, Completed, _) | (TimerKind::K, Completed, _) | (TimerKind::M, Accepted, _) => {
+                info.client.state = Terminated;
+            }
+            (TimerKind::Expire, Terminated, Terminated) => {
+                debug!(branch = %key.branch, method = %key.method, age_secs = info.created_at.elapsed().as_secs(), "İşlem kaydının süresi doldu.");
+                self.remove(key);
+                return;
+            }
+            // Hâlâ etkin olan bir işlem silinmez; süre yeniden başlatılır.
+            (TimerKind::Expire, _, _) => {
+                timers.push((TimerKind::Expire, TRANSACTION_TTL));
+            }
+            // Durumu değişmiş bir işleme ait zamanlayıcılar yok sayılır.
+            _ => return,
+        }
+        for (kind, delay) in timers {
+            self.schedule(key, id, kind, delay);
+        }
+        if let Some((status, response)) = timeout_response {
+            self.on_server_response(key, status, &response);
+        }
+        if let Some(cancel) = timer_c_cancel {
+            outgoing.push((cancel.client.request.clone(), cancel.client.target));
+            self.insert(key.with_method("CANCEL"), cancel);
+        }
+        self.remove_if_terminated(key);
+    }
+
+    /// Her iki tarafı da sonlanmış işlemleri hemen siler. Çağrı boyunca gereken bilgiler
+    /// (route set, remote target) işlem kaydında değil, diyalog katmanında tutulur.
+    fn remove_if_terminated(&mut self, key: &TransactionKey) {
+        if let Some(info) = self.entries.get(key) {
+            if info.client.state == TransactionState::Terminated
+                && info.server.state == TransactionState::Terminated
+            {
+                self.remove(key);
+            }
+        }
+    }
+}
+
+/// Zamanlayıcı çarklarını süreli olarak ilerletir; yeniden iletimleri ve zaman aşımı yanıtlarını gönderir.
+/// Parçalar sırayla kilitlenir; bir parçanın zamanlayıcıları işlenirken diğer parçalar paket işlemeye devam eder.
+pub async fn run_timers(transactions: Transactions, transport: Arc<TransportLayer>, config: Arc<AppConfig>) {
+    let mut interval = tokio::time::interval(TIMER_TICK);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        let now = Instant::now();
+        let mut outgoing = Vec::new();
+        for shard in transactions.shards() {
+            let mut guard = shard.lock().await;
+            let fired = guard.process_timers(now, &config);
+            if !fired.is_empty() {
+                debug!(
+                    active_transactions = guard.entries.len(),
+                    pending_timers = guard.timers.len(),
+                    "Zamanlayıcılar işlendi."
+                );
+            }
+            outgoing.extend(fired);
+        }
+        for (packet, target) in outgoing {
+            if let Err(e) = transport.send(&packet, target).await {
+                error!(error = %e, target = %target, "Zamanlayıcı kaynaklı paket gönderilemedi.");
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARRIER: &str = "192.0.2.10:5060";
+    const SIGNALING: &str = "10.0.0.1:5060";
+
+    fn request(method: &str, branch: &str) -> SipMessage {
+        let packet = format!(
+            "{method} sip:1000@203.0.113.1 SIP/2.0\r\n\
+Via: SIP/2.0/UDP {CARRIER};branch={branch}\r\n\
+Max-Forwards: 70\r\n\
+From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+To: <sip:1000@203.0.113.1>\r\n\
+Call-ID: tx-test\r\n\
+CSeq: 1 {method}\r\n\
+Content-Length: 0\r\n\r\n"
+        );
+        SipMessage::parse(packet.as_bytes()).unwrap()
+    }
+
+    /// Bir sonraki adımdan gelen yanıt; Via'sı gateway'in ürettiği `client_branch` değerini taşır.
+    fn response(status: u16, reason: &str, method: &str, client_branch: &str) -> SipMessage {
+        let packet = format!(
+            "SIP/2.0 {status} {reason}\r\n\
+Via: SIP/2.0/UDP 203.0.113.1:5060;branch={client_branch}\r\n\
+From: <sip:2000@192.0.2.10>;tag=a1\r\n\
+To: <sip:1000@203.0.113.1>;tag=b1\r\n\
+Call-ID: tx-test\r\n\
+CSeq: 1 {method}\r\n\
+Content-Length: 0\r\n\r\n"
+        );
+        SipMessage::parse(packet.as_bytes()).unwrap()
+    }
+
+    /// Operatörden gelen isteği, iç servise iletilmiş gibi depoya ekler.
+    fn forward(store: &mut TransactionStore, msg: &SipMessage, client_branch: &str) -> TransactionKey {
+        let key = TransactionKey::for_request(msg).unwrap();
+        let info = TransactionInfo::new(
+            Direction::Inbound,
+            msg,
+            Peer::udp(CARRIER.parse().unwrap()),
+            msg.to_bytes(),
+            client_branch.to_string(),
+            Peer::internal(SIGNALING.parse().unwrap()),
+        );
+        store.insert(key.clone(), info);
+        key
+    }
+
+    fn starts_with(outgoing: &[Outgoing], prefix: &str) -> usize {
+        outgoing.iter().filter(|(packet, _)| packet.starts_with(prefix.as_bytes())).count()
+    }
+
+    #[test]
+    fn transaction_key_matches_branch_sent_by_and_method() {
+        let invite = request("INVITE", "z9hG4bK.a");
+        let key = TransactionKey::for_request(&invite).unwrap();
+        assert_eq!(TransactionKey::for_request(&request("ACK", "z9hG4bK.a")), Some(key.clone()));
+        assert_eq!(TransactionKey::for_request(&request("CANCEL", "z9hG4bK.a")), Some(key.with_method("CANCEL")));
+        assert_ne!(TransactionKey::for_request(&request("INVITE", "z9hG4bK.b")), Some(key.clone()));
+
+        let other_host = String::from_utf8(invite.to_bytes()).unwrap().replace(CARRIER, "192.0.2.11:5060");
+        let other_host = SipMessage::parse(other_host.as_bytes()).unwrap();
+        assert_ne!(TransactionKey::for_request(&other_host), Some(key));
+    }
+
+    #[test]
+    fn retransmitted_request_replays_last_response() {
+        let mut store = TransactionStore::new();
+        let invite = request("INVITE", "z9hG4bK.a");
+        let key = forward(&mut store, &invite, "z9hG4bK.out");
+        assert!(store.on_request_retransmission(&key).is_none());
+
+        store.on_server_response(&key, 180, b"SIP/2.0 180 Ringing\r\n\r\n");
+        let (packet, target) = store.on_request_retransmission(&key).unwrap();
+        assert_eq!(packet, b"SIP/2.0 180 Ringing\r\n\r\n");
+        assert_eq!(target, Peer::udp(CARRIER.parse().unwrap()));
+
+        store.on_server_response(&key, 486, b"SIP/2.0 486 Busy Here\r\n\r\n");
+        assert_eq!(store.on_request_retransmission(&key).unwrap().0, b"SIP/2.0 486 Busy Here\r\n\r\n");
+    }
+
+    #[test]
+    fn ack_for_non_2xx_stops_timer_g() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        let key = forward(&mut store, &request("INVITE", "z9hG4bK.a"), "z9hG4bK.out");
+        store.on_server_response(&key, 486, b"SIP/2.0 486 Busy Here\r\n\r\n");
+
+        let start = Instant::now();
+        let fired = store.process_timers(start + Duration::from_millis(600), &config);
+        assert_eq!(starts_with(&fired, "SIP/2.0 486"), 1, "Timer G yanıtı yeniden iletmeli");
+
+        assert!(store.on_ack(&key));
+        let fired = store.process_timers(start + Duration::from_secs(5), &config);
+        assert_eq!(starts_with(&fired, "SIP/2.0 486"), 0, "ACK'ten sonra Timer G durmalı");
+        assert_eq!(store.get(&key).unwrap().server.state, TransactionState::Confirmed);
+    }
+
+    #[test]
+    fn duplicate_provisional_is_not_forwarded() {
+        let mut store = TransactionStore::new();
+        let key = forward(&mut store, &request("INVITE", "z9hG4bK.a"), "z9hG4bK.out");
+        let ringing = response(180, "Ringing", "INVITE", "z9hG4bK.out");
+        assert!(matches!(store.on_client_response(&key, &ringing, 180), ResponseAction::Forward));
+        assert!(matches!(store.on_client_response(&key, &ringing, 180), ResponseAction::Absorb));
+        assert_eq!(store.get(&key).unwrap().client.state, TransactionState::Proceeding);
+    }
+
+    #[test]
+    fn non_2xx_final_response_is_acked_and_duplicate_resends_ack() {
+        let mut store = TransactionStore::new();
+        let key = forward(&mut store, &request("INVITE", "z9hG4bK.a"), "z9hG4bK.out");
+        let busy = response(486, "Busy Here", "INVITE", "z9hG4bK.out");
+        let ResponseAction::ForwardWithAck((ack, target)) = store.on_client_response(&key, &busy, 486) else {
+            panic!("ilk 486 ACK ile iletilmeli");
+        };
+        assert!(ack.starts_with(b"ACK sip:1000@203.0.113.1 SIP/2.0"));
+        assert_eq!(target, Peer::internal(SIGNALING.parse().unwrap()));
+        assert!(matches!(store.on_client_response(&key, &busy, 486), ResponseAction::ResendAck(_)));
+    }
+
+    #[test]
+    fn cancel_before_provisional_is_held_until_first_1xx() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        let invite = request("INVITE", "z9hG4bK.a");
+        let invite_key = forward(&mut store, &invite, "z9hG4bK.out");
+
+        let cancel = request("CANCEL", "z9hG4bK.a");
+        let cancel_key = TransactionKey::for_request(&cancel).unwrap();
+        let forwarded = build_cancel_for_request(&invite.to_bytes(), None).unwrap();
+        let info = TransactionInfo::new(
+            Direction::Inbound,
+            &cancel,
+            Peer::udp(CARRIER.parse().unwrap()),
+            forwarded.clone(),
+            "z9hG4bK.out".to_string(),
+            Peer::internal(SIGNALING.parse().unwrap()),
+        );
+        assert!(store.on_cancel(cancel_key.clone(), info, b"SIP/2.0 200 OK\r\n\r\n").is_none());
+        assert_eq!(store.on_request_retransmission(&cancel_key).unwrap().0, b"SIP/2.0 200 OK\r\n\r\n");
+        let fired = store.process_timers(Instant::now() + Duration::from_secs(1), &config);
+        assert_eq!(starts_with(&fired, "CANCEL"), 0, "CANCEL geçici yanıttan önce gönderilmemeli");
+
+        let trying = response(100, "Trying", "INVITE", "z9hG4bK.out");
+        store.on_client_response(&invite_key, &trying, 100);
+        let (packet, target) = store.start_pending_cancel(&invite_key).unwrap();
+        assert_eq!(packet, forwarded);
+        assert_eq!(target, Peer::internal(SIGNALING.parse().unwrap()));
+        assert!(store.start_pending_cancel(&invite_key).is_none(), "CANCEL bir kez gönderilmeli");
+    }
+
+    #[test]
+    fn timer_c_cancels_downstream_then_answers_487_upstream() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        let key = forward(&mut store, &request("INVITE", "z9hG4bK.a"), "z9hG4bK.out");
+        store.on_client_response(&key, &response(180, "Ringing", "INVITE", "z9hG4bK.out"), 180);
+        store.on_server_response(&key, 180, b"SIP/2.0 180 Ringing\r\n\r\n");
+
+        let start = Instant::now();
+        let fired = store.process_timers(start + Duration::from_secs(60), &config);
+        assert_eq!(starts_with(&fired, "CANCEL"), 0, "Timer C süresinden önce CANCEL gönderilmemeli");
+
+        let fired = store.process_timers(start + TIMER_C + Duration::from_secs(1), &config);
+        let cancels: Vec<_> = fired.iter().filter(|(packet, _)| packet.starts_with(b"CANCEL")).collect();
+        assert_eq!(cancels.len(), 1);
+        assert_eq!(cancels[0].1, Peer::internal(SIGNALING.parse().unwrap()));
+        assert!(store.get(&key).unwrap().client.cancelled);
+
+        let fired = store.process_timers(start + TIMER_C + TIMEOUT_64T1 + Duration::from_secs(2), &config);
+        let responses: Vec<_> = fired.iter().filter(|(packet, _)| packet.starts_with(b"SIP/2.0 487")).collect();
+        assert_eq!(responses.len(), 1, "iptal edilen taraf yanıt vermezse 487 üretilmeli");
+        assert_eq!(responses[0].1, Peer::udp(CARRIER.parse().unwrap()));
+    }
+
+    #[test]
+    fn timer_b_answers_invite_with_408() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        forward(&mut store, &request("INVITE", "z9hG4bK.a"), "z9hG4bK.out");
+        let fired = store.process_timers(Instant::now() + TIMEOUT_64T1 + Duration::from_secs(1), &config);
+        assert_eq!(starts_with(&fired, "SIP/2.0 408"), 1);
+        assert!(starts_with(&fired, "INVITE") > 0, "Timer A isteği yeniden iletmeli");
+    }
+
+    #[test]
+    fn timer_f_terminates_non_invite_without_408() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        let key = forward(&mut store, &request("BYE", "z9hG4bK.a"), "z9hG4bK.out");
+        let fired = store.process_timers(Instant::now() + TIMEOUT_64T1 + Duration::from_secs(1), &config);
+        assert_eq!(starts_with(&fired, "SIP/2.0"), 0, "INVITE olmayan istekte 408 üretilmemeli (RFC 4320)");
+        assert!(starts_with(&fired, "BYE") > 0, "Timer E isteği yeniden iletmeli");
+        assert!(!store.contains_key(&key));
+    }
+
+    #[test]
+    fn reliable_transport_does_not_retransmit() {
+        let config = AppConfig::for_tests();
+        let mut store = TransactionStore::new();
+        let invite = request("INVITE", "z9hG4bK.a");
+        let key = TransactionKey::for_request(&invite).unwrap();
+        let target = Peer::new("192.0.2.20:5060".parse().unwrap(), crate::transport::Transport::Tcp);
+        let source = Peer::internal(SIGNALING.parse().unwrap());
+        let info = TransactionInfo::new(Direction::Outbound, &invite, source, invite.to_bytes(), "z9hG4bK.out".to_string(), target);
+        store.insert(key, info);
+        let fired = store.process_timers(Instant::now() + Duration::from_secs(10), &config);
+        assert!(fired.is_empty());
+    }
 }
\ No newline at end of file
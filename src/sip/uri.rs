@@ -0,0 +1,440 @@
+// File: src/sip/uri.rs
+
+use std::fmt;
+
+/// Desteklenen URI şemaları.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Sip,
+    Sips,
+    Tel,
+}
+
+impl Scheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Sip => "sip",
+            Scheme::Sips => "sips",
+            Scheme::Tel => "tel",
+        }
+    }
+}
+
+/// `;ad=değer` biçimindeki parametrelerin sırası korunmuş listesi.
+/// Değersiz parametreler (`;lr`, `;rport`) `None` değeriyle saklanır.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params(Vec<(String, Option<String>)>);
+
+impl Params {
+    /// `;a=1;b;c=3` biçimindeki bir metni ayrıştırır. Baştaki `;` isteğe bağlıdır.
+    pub fn parse(input: &str) -> Self {
+        let params = input
+            .split(';')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| match p.split_once('=') {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                None => (p.to_string(), None),
+            })
+            .collect();
+        Params(params)
+    }
+
+    /// Parametrenin var olup olmadığını ve değerini döndürür.
+    /// Dış `Option` parametrenin varlığını, iç `Option` değerini gösterir.
+    pub fn get(&self, name: &str) -> Option<Option<&str>> {
+        self.0
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref())
+    }
+
+    /// Parametrenin değerini döndürür; parametre yoksa veya değersizse `None` döner.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.get(name).flatten()
+    }
+
+    /// Parametrenin, değerli veya değersiz olarak bulunup bulunmadığını döndürür.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Parametreyi günceller; yoksa sona ekler. Mevcut parametrenin konumu korunur.
+    pub fn set(&mut self, name: &str, value: Option<String>) {
+        match self.0.iter_mut().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some(entry) => entry.1 = value,
+            None => self.0.push((name.to_string(), value)),
+        }
+    }
+
+    /// Parametreyi, varsa, listeden çıkarır.
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.0 {
+            match value {
+                Some(value) => write!(f, ";{}={}", name, value)?,
+                None => write!(f, ";{}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ayrıştırılmış bir `sip:`, `sips:` veya `tel:` URI'si.
+/// `tel:` URI'lerinde telefon numarası `user` alanında tutulur ve `host` boştur.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SipUri {
+    pub scheme: Scheme,
+    /// Kullanıcı kısmı; varsa `:şifre` eki de dahil olmak üzere olduğu gibi saklanır.
+    pub user: Option<String>,
+    /// Sunucu adı veya IP adresi. IPv6 adresleri köşeli parantezsiz saklanır.
+    pub host: String,
+    pub port: Option<u16>,
+    pub params: Params,
+    /// `?ad=değer&...` biçimindeki URI başlıkları, olduğu gibi.
+    pub headers: Option<String>,
+}
+
+impl SipUri {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (scheme_str, rest) = input.split_once(':')?;
+        let scheme = match scheme_str.to_ascii_lowercase().as_str() {
+            "sip" => Scheme::Sip,
+            "sips" => Scheme::Sips,
+            "tel" => Scheme::Tel,
+            _ => return None,
+        };
+
+        let (rest, headers) = match rest.split_once('?') {
+            Some((rest, headers)) => (rest, Some(headers.to_string())),
+            None => (rest, None),
+        };
+
+        if scheme == Scheme::Tel {
+            let (number, params) = rest.split_once(';').unwrap_or((rest, ""));
+            if number.is_empty() {
+                return None;
+            }
+            return Some(SipUri {
+                scheme,
+                user: Some(number.to_string()),
+                host: String::new(),
+                port: None,
+                params: Params::parse(params),
+                headers,
+            });
+        }
+
+        // Kullanıcı kısmı ';' içerebileceği için önce '@' aranır (RFC 3261 §19.1.1).
+        let (user, host_part) = match rest.rfind('@') {
+            Some(pos) => (Some(rest[..pos].to_string()), &rest[pos + 1..]),
+            None => (None, rest),
+        };
+        let (hostport, params) = host_part.split_once(';').unwrap_or((host_part, ""));
+        let (host, port) = parse_hostport(hostport)?;
+
+        Some(SipUri {
+            scheme,
+            user,
+            host,
+            port,
+            params: Params::parse(params),
+            headers,
+        })
+    }
+
+    /// Sunucu adı ile portu değiştirir; kullanıcı kısmı, parametreler ve başlıklar korunur.
+    pub fn set_host_port(&mut self, host: &str, port: Option<u16>) {
+        self.host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+        self.port = port;
+    }
+}
+
+impl fmt::Display for SipUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.scheme.as_str())?;
+        if self.scheme == Scheme::Tel {
+            write!(f, "{}", self.user.as_deref().unwrap_or_default())?;
+        } else {
+            if let Some(user) = &self.user {
+                write!(f, "{}@", user)?;
+            }
+            write!(f, "{}", format_host(&self.host))?;
+            if let Some(port) = self.port {
+                write!(f, ":{}", port)?;
+            }
+        }
+        write!(f, "{}", self.params)?;
+        if let Some(headers) = &self.headers {
+            write!(f, "?{}", headers)?;
+        }
+        Ok(())
+    }
+}
+
+/// `From`, `To`, `Contact`, `Route`, `Record-Route` gibi başlıklarda kullanılan
+/// `"Görünen Ad" <uri>;parametreler` yapısı (RFC 3261 §20.10 name-addr / addr-spec).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameAddr {
+    /// Görünen ad, tırnaklarıyla birlikte olduğu gibi saklanır.
+    pub display_name: Option<String>,
+    pub uri: SipUri,
+    /// Başlık parametreleri (`tag`, `expires`, `q`...).
+    pub params: Params,
+    /// Orijinal değer açılı parantez kullanıyor muydu? Yeniden yazımda biçim korunur.
+    angle_brackets: bool,
+}
+
+impl NameAddr {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(open) = find_unquoted(input, '<') {
+            let close = open + input[open..].find('>')?;
+            let display_name = input[..open].trim();
+            let uri = SipUri::parse(&input[open + 1..close])?;
+            Some(NameAddr {
+                display_name: (!display_name.is_empty()).then(|| display_name.to_string()),
+                uri,
+                params: Params::parse(&input[close + 1..]),
+                angle_brackets: true,
+            })
+        } else {
+            // addr-spec biçiminde ';' ile başlayan kısımlar URI'ye değil başlığa aittir.
+            let (uri, params) = input.split_once(';').unwrap_or((input, ""));
+            Some(NameAddr {
+                display_name: None,
+                uri: SipUri::parse(uri)?,
+                params: Params::parse(params),
+                angle_brackets: false,
+            })
+        }
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.params.value("tag")
+    }
+
+    /// `expires` parametresinin saniye cinsinden değeri; yoksa veya sayı değilse `None` döner.
+    pub fn expires(&self) -> Option<u32> {
+        self.params.value("expires").and_then(|v| v.parse().ok())
+    }
+}
+
+impl fmt::Display for NameAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(display_name) = &self.display_name {
+            write!(f, "{} ", display_name)?;
+        }
+        // URI parametresi veya başlığı içeren URI'ler açılı parantez olmadan yazılamaz.
+        let needs_brackets = self.angle_brackets
+            || self.display_name.is_some()
+            || self.uri.params != Params::default()
+            || self.uri.headers.is_some();
+        if needs_brackets {
+            write!(f, "<{}>", self.uri)?;
+        } else {
+            write!(f, "{}", self.uri)?;
+        }
+        write!(f, "{}", self.params)
+    }
+}
+
+/// Virgülle ayrılmış bir başlık değerini (`Contact: <a>, <b>`) öğelerine böler.
+/// Tırnak ve açılı parantez içindeki virgüller ayırıcı sayılmaz.
+pub fn split_header_list(value: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut in_quotes = false;
+    let mut in_brackets = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '<' if !in_quotes => in_brackets = true,
+            '>' if !in_quotes => in_brackets = false,
+            ',' if !in_quotes && !in_brackets => {
+                items.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(value[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// `host`, `host:port`, `[v6]` veya `[v6]:port` biçimindeki bir metni ayrıştırır.
+/// Köşeli parantezsiz yazılmış bir IPv6 adresi (birden fazla `:`) portsuz sunucu adı sayılır;
+/// `host:` gibi boş port da port yazılmamış kabul edilir.
+pub fn parse_hostport(input: &str) -> Option<(String, Option<u16>)> {
+    let input = input.trim();
+    let (host, port) = if let Some(rest) = input.strip_prefix('[') {
+        let close = rest.find(']')?;
+        match rest[close + 1..].strip_prefix(':') {
+            Some(port) => (&rest[..close], port),
+            None if rest[close + 1..].is_empty() => (&rest[..close], ""),
+            None => return None,
+        }
+    } else if input.matches(':').count() > 1 {
+        (input, "")
+    } else {
+        input.split_once(':').unwrap_or((input, ""))
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        "" => None,
+        port => Some(port.parse().ok()?),
+    };
+    Some((host.to_string(), port))
+}
+
+/// Sunucu adını URI/Via içinde kullanılacak biçime getirir; IPv6 adresleri köşeli paranteze alınır.
+pub fn format_host(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+fn find_unquoted(input: &str, target: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == target && !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri_round_trip(input: &str) -> SipUri {
+        let uri = SipUri::parse(input).unwrap();
+        assert_eq!(uri.to_string(), input);
+        uri
+    }
+
+    fn name_addr_round_trip(input: &str) -> NameAddr {
+        let name_addr = NameAddr::parse(input).unwrap();
+        assert_eq!(name_addr.to_string(), input);
+        name_addr
+    }
+
+    #[test]
+    fn sip_sips_and_tel_uris_round_trip() {
+        let sip = uri_round_trip("sip:alice@example.com:5070;transport=tcp;lr");
+        assert_eq!(sip.scheme, Scheme::Sip);
+        assert_eq!(sip.user.as_deref(), Some("alice"));
+        assert_eq!(sip.host, "example.com");
+        assert_eq!(sip.port, Some(5070));
+        assert_eq!(sip.params.value("transport"), Some("tcp"));
+        assert!(sip.params.contains("lr"));
+
+        let sips = uri_round_trip("sips:bob@192.0.2.4");
+        assert_eq!(sips.scheme, Scheme::Sips);
+        assert_eq!(sips.port, None);
+
+        let tel = uri_round_trip("tel:+90-212-555-0100;phone-context=example.com");
+        assert_eq!(tel.scheme, Scheme::Tel);
+        assert_eq!(tel.user.as_deref(), Some("+90-212-555-0100"));
+        assert_eq!(tel.params.value("phone-context"), Some("example.com"));
+
+        assert!(SipUri::parse("http://example.com").is_none());
+        assert!(SipUri::parse("tel:").is_none());
+    }
+
+    #[test]
+    fn ipv6_host_with_port_round_trips() {
+        let uri = uri_round_trip("sip:alice@[2001:db8::1]:5070;transport=udp");
+        assert_eq!(uri.host, "2001:db8::1");
+        assert_eq!(uri.port, Some(5070));
+    }
+
+    #[test]
+    fn user_part_may_contain_semicolon() {
+        let uri = uri_round_trip("sip:+902125550100;npdi;rn=+902125559999@gw.example.com;user=phone");
+        assert_eq!(uri.user.as_deref(), Some("+902125550100;npdi;rn=+902125559999"));
+        assert_eq!(uri.host, "gw.example.com");
+        assert_eq!(uri.params.value("user"), Some("phone"));
+    }
+
+    #[test]
+    fn uri_headers_are_kept() {
+        let uri = uri_round_trip("sip:carol@example.com;method=REFER?Replaces=abc%40host&Subject=test");
+        assert_eq!(uri.params.value("method"), Some("REFER"));
+        assert_eq!(uri.headers.as_deref(), Some("Replaces=abc%40host&Subject=test"));
+
+        let contact = name_addr_round_trip("<sip:carol@example.com?Subject=test>");
+        assert_eq!(contact.uri.headers.as_deref(), Some("Subject=test"));
+    }
+
+    #[test]
+    fn quoted_display_name_may_contain_angle_bracket_and_comma() {
+        let name_addr = name_addr_round_trip(r#""Smith, John <Sales>" <sip:john@example.com>;tag=a1"#);
+        assert_eq!(name_addr.display_name.as_deref(), Some(r#""Smith, John <Sales>""#));
+        assert_eq!(name_addr.uri.user.as_deref(), Some("john"));
+        assert_eq!(name_addr.tag(), Some("a1"));
+
+        let list = split_header_list(r#""Smith, John" <sip:john@example.com>, <sip:jane@example.com>"#);
+        assert_eq!(list, [r#""Smith, John" <sip:john@example.com>"#, "<sip:jane@example.com>"]);
+    }
+
+    #[test]
+    fn addr_spec_parameters_belong_to_the_header() {
+        let to = name_addr_round_trip("sip:bob@example.com;tag=b1");
+        assert_eq!(to.tag(), Some("b1"));
+        assert_eq!(to.uri.params, Params::default());
+
+        let contact = name_addr_round_trip("sip:bob@192.0.2.4:5060;expires=3600;q=0.5");
+        assert_eq!(contact.expires(), Some(3600));
+        assert_eq!(contact.params.value("q"), Some("0.5"));
+        assert_eq!(contact.uri.port, Some(5060));
+    }
+
+    #[test]
+    fn uri_with_parameters_is_written_in_angle_brackets() {
+        let mut contact = NameAddr::parse("sip:bob@192.0.2.4;expires=60").unwrap();
+        contact.uri.params.set("transport", Some("tcp".to_string()));
+        assert_eq!(contact.to_string(), "<sip:bob@192.0.2.4;transport=tcp>;expires=60");
+    }
+
+    #[test]
+    fn parse_hostport_accepts_all_host_forms() {
+        assert_eq!(parse_hostport("example.com"), Some(("example.com".to_string(), None)));
+        assert_eq!(parse_hostport("example.com:5070"), Some(("example.com".to_string(), Some(5070))));
+        assert_eq!(parse_hostport("example.com:"), Some(("example.com".to_string(), None)));
+        assert_eq!(parse_hostport("[2001:db8::1]"), Some(("2001:db8::1".to_string(), None)));
+        assert_eq!(parse_hostport("[2001:db8::1]:5070"), Some(("2001:db8::1".to_string(), Some(5070))));
+        assert_eq!(parse_hostport("[2001:db8::1]:"), Some(("2001:db8::1".to_string(), None)));
+        assert_eq!(parse_hostport("2001:db8::1"), Some(("2001:db8::1".to_string(), None)));
+        assert_eq!(parse_hostport("::1"), Some(("::1".to_string(), None)));
+        assert_eq!(parse_hostport(""), None);
+        assert_eq!(parse_hostport(":5060"), None);
+        assert_eq!(parse_hostport("example.com:port"), None);
+        assert_eq!(parse_hostport("[2001:db8::1"), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,245 @@
+// File: src/sip/via.rs
+
+use crate::sip::uri::{format_host, parse_hostport, split_header_list, Params};
+use crate::transport::Transport;
+use rand::Rng;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+/// RFC 3261'e uygun branch değerlerinin taşıması gereken sihirli önek (§8.1.1.7).
+pub const BRANCH_MAGIC_COOKIE: &str = "z9hG4bK";
+
+/// Gateway'in oluşturduğu istekler için yeni ve benzersiz bir branch değeri üretir.
+pub fn new_branch() -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    format!("{}.{}", BRANCH_MAGIC_COOKIE, random)
+}
+
+/// Ayrıştırılmış tek bir `Via` değeri: `SIP/2.0/UDP host:port;branch=...;rport;received=...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Via {
+    /// Protokol adı ve sürümü, genellikle `SIP/2.0`.
+    pub protocol: String,
+    /// Taşıma katmanı (`UDP`, `TCP`, `TLS`, `WS`...), büyük harfle saklanır.
+    pub transport: String,
+    /// sent-by sunucu adı veya IP adresi. IPv6 adresleri köşeli parantezsiz saklanır.
+    pub host: String,
+    pub port: Option<u16>,
+    pub params: Params,
+}
+
+impl Via {
+    /// Gateway'in kendi adına eklediği Via değerini oluşturur.
+    pub fn new(transport: &str, host: &str, port: u16, branch: &str) -> Self {
+        let mut params = Params::default();
+        params.set("branch", Some(branch.to_string()));
+        Via {
+            protocol: "SIP/2.0".to_string(),
+            transport: transport.to_ascii_uppercase(),
+            host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+            port: Some(port),
+            params,
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        // "SIP / 2.0 / UDP" gibi '/' çevresinde boşluk içeren yazımlar da geçerlidir (RFC 3261 §25.1).
+        let mut normalized = input.trim().to_string();
+        while normalized.contains(" /") || normalized.contains("/ ") || normalized.contains("\t/") || normalized.contains("/\t") {
+            normalized = normalized.replace(" /", "/").replace("/ ", "/").replace("\t/", "/").replace("/\t", "/");
+        }
+
+        let (sent_protocol, rest) = normalized.split_once(char::is_whitespace)?;
+        let (protocol, transport) = sent_protocol.rsplit_once('/')?;
+        if !protocol.contains('/') || transport.is_empty() {
+            return None;
+        }
+
+        let rest = rest.trim();
+        let (sent_by, params) = rest.split_once(';').unwrap_or((rest, ""));
+        let (host, port) = parse_hostport(sent_by)?;
+
+        Some(Via {
+            protocol: protocol.to_string(),
+            transport: transport.to_ascii_uppercase(),
+            host,
+            port,
+            params: Params::parse(params),
+        })
+    }
+
+    /// Bir veya birden fazla `Via` başlık değerini (`Via: a, b` biçimi dahil) sırasıyla ayrıştırır.
+    /// Ayrıştırılamayan değerler atlanır.
+    pub fn parse_list<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<Via> {
+        values
+            .into_iter()
+            .flat_map(split_header_list)
+            .filter_map(Via::parse)
+            .collect()
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.params.value("branch")
+    }
+
+    pub fn received(&self) -> Option<IpAddr> {
+        self.params.value("received").and_then(|v| v.trim_start_matches('[').trim_end_matches(']').parse().ok())
+    }
+
+    /// `rport` parametresi: dış `Option` varlığını, iç `Option` doldurulmuş port değerini gösterir.
+    pub fn rport(&self) -> Option<Option<u16>> {
+        self.params.get("rport").map(|value| value.and_then(|v| v.parse().ok()))
+    }
+
+    /// `maddr` parametresi. Yalnızca ayrıştırılır; yanıt adresi hesaplanırken kullanılmaz.
+    pub fn maddr(&self) -> Option<&str> {
+        self.params.value("maddr")
+    }
+
+    /// Çok noktaya yayın isteklerindeki `ttl` parametresi.
+    pub fn ttl(&self) -> Option<u8> {
+        self.params.value("ttl").and_then(|v| v.parse().ok())
+    }
+
+    /// Karşılaştırmalarda kullanılan `host:port` biçimindeki sent-by değeri.
+    /// Port yazılmamışsa taşıma katmanının varsayılan portu kullanılır (RFC 3261 §18.2.1).
+    pub fn sent_by(&self) -> String {
+        format!("{}:{}", format_host(&self.host.to_ascii_lowercase()), self.port.unwrap_or(self.default_port()))
+    }
+
+    fn default_port(&self) -> u16 {
+        Transport::from_token(&self.transport).map_or(5060, Transport::default_port)
+    }
+
+    /// İsteğin geldiği gerçek kaynak adresine göre `received` ve `rport` parametrelerini doldurur.
+    /// RFC 3261 §18.2.1: sent-by kaynak IP'den farklıysa `received` eklenir.
+    /// RFC 3581 §4: `rport` değersiz gelmişse kaynak port ile doldurulur ve `received` her durumda eklenir.
+    pub fn apply_received(&mut self, source: SocketAddr) {
+        let rport_requested = self.rport().is_some();
+        let sent_by_matches = self.host.parse::<IpAddr>().ok() == Some(source.ip());
+        if rport_requested || !sent_by_matches {
+            self.params.set("received", Some(source.ip().to_string()));
+        }
+        if rport_requested {
+            self.params.set("rport", Some(source.port().to_string()));
+        }
+    }
+
+    /// Bu Via'yı ekleyen istemciye yanıtın gönderileceği adresi hesaplar (RFC 3261 §18.2.2, RFC 3581 §4).
+    /// sent-by bir alan adıysa ve `received` yoksa adres çözülemez; bu durumda `None` döner.
+    /// Çok noktaya yayın (multicast) yanıtlar desteklenmediği için `maddr` dikkate alınmaz; aksi halde
+    /// doğrulanmamış bir gönderici yanıtları istediği adrese yönlendirebilirdi.
+    pub fn response_addr(&self) -> Option<SocketAddr> {
+        let default_port = self.default_port();
+        let ip = match self.received() {
+            Some(ip) => ip,
+            None => self.host.parse::<IpAddr>().ok()?,
+        };
+        let port = match self.rport() {
+            Some(Some(rport)) => rport,
+            _ => self.port.unwrap_or(default_port),
+        };
+        Some(SocketAddr::new(ip, port))
+    }
+}
+
+impl fmt::Display for Via {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} {}", self.protocol, self.transport, format_host(&self.host))?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        write!(f, "{}", self.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn via(value: &str) -> Via {
+        Via::parse(value).unwrap()
+    }
+
+    #[test]
+    fn parse_list_splits_comma_separated_values_in_order() {
+        let vias = Via::parse_list([
+            "SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK.a, SIP/2.0/TCP proxy.example.com;branch=z9hG4bK.b",
+            "SIP / 2.0 / TLS 192.0.2.3:5061;branch=z9hG4bK.c",
+        ]);
+        let branches: Vec<_> = vias.iter().map(|via| via.branch().unwrap()).collect();
+        assert_eq!(branches, ["z9hG4bK.a", "z9hG4bK.b", "z9hG4bK.c"]);
+        assert_eq!(vias[1].transport, "TCP");
+        assert_eq!(vias[1].host, "proxy.example.com");
+        assert_eq!(vias[1].port, None);
+        assert_eq!(vias[2].protocol, "SIP/2.0");
+    }
+
+    #[test]
+    fn ipv6_sent_by_is_parsed_and_formatted_with_brackets() {
+        let parsed = via("SIP/2.0/UDP [2001:db8::1]:5070;branch=z9hG4bK.a;rport");
+        assert_eq!(parsed.host, "2001:db8::1");
+        assert_eq!(parsed.port, Some(5070));
+        assert_eq!(parsed.sent_by(), "[2001:db8::1]:5070");
+        assert_eq!(parsed.to_string(), "SIP/2.0/UDP [2001:db8::1]:5070;branch=z9hG4bK.a;rport");
+        assert_eq!(parsed.response_addr(), Some("[2001:db8::1]:5070".parse().unwrap()));
+    }
+
+    #[test]
+    fn received_is_added_only_when_source_differs_from_sent_by() {
+        let mut same = via("SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK.a");
+        same.apply_received("192.0.2.1:5060".parse().unwrap());
+        assert_eq!(same.received(), None);
+
+        let mut behind_nat = via("SIP/2.0/UDP 10.1.1.1:5060;branch=z9hG4bK.a");
+        behind_nat.apply_received("198.51.100.7:5060".parse().unwrap());
+        assert_eq!(behind_nat.received(), Some("198.51.100.7".parse().unwrap()));
+        assert_eq!(behind_nat.rport(), None);
+
+        let mut by_name = via("SIP/2.0/UDP pbx.example.com;branch=z9hG4bK.a");
+        by_name.apply_received("198.51.100.7:5060".parse().unwrap());
+        assert_eq!(by_name.received(), Some("198.51.100.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn empty_rport_is_filled_with_source_port_and_forces_received() {
+        let mut parsed = via("SIP/2.0/UDP 192.0.2.1:5060;rport;branch=z9hG4bK.a");
+        parsed.apply_received("192.0.2.1:40123".parse().unwrap());
+        assert_eq!(parsed.rport(), Some(Some(40123)));
+        assert_eq!(parsed.received(), Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(parsed.to_string(), "SIP/2.0/UDP 192.0.2.1:5060;rport=40123;branch=z9hG4bK.a;received=192.0.2.1");
+    }
+
+    #[test]
+    fn response_addr_uses_sent_by_without_received_or_rport() {
+        assert_eq!(via("SIP/2.0/UDP 192.0.2.1:5070;branch=z9hG4bK.a").response_addr(), Some("192.0.2.1:5070".parse().unwrap()));
+        assert_eq!(via("SIP/2.0/UDP 192.0.2.1;branch=z9hG4bK.a").response_addr(), Some("192.0.2.1:5060".parse().unwrap()));
+        assert_eq!(via("SIP/2.0/TLS 192.0.2.1;branch=z9hG4bK.a").response_addr(), Some("192.0.2.1:5061".parse().unwrap()));
+        assert_eq!(via("SIP/2.0/UDP pbx.example.com;branch=z9hG4bK.a").response_addr(), None);
+    }
+
+    #[test]
+    fn response_addr_prefers_received_and_rport() {
+        let received = via("SIP/2.0/UDP 10.1.1.1:5070;branch=z9hG4bK.a;received=198.51.100.7");
+        assert_eq!(received.response_addr(), Some("198.51.100.7:5070".parse().unwrap()));
+
+        let both = via("SIP/2.0/UDP pbx.example.com:5070;branch=z9hG4bK.a;received=198.51.100.7;rport=40123");
+        assert_eq!(both.response_addr(), Some("198.51.100.7:40123".parse().unwrap()));
+
+        // Değersiz rport gelen port bilinmediği için sent-by portuna düşer.
+        let empty_rport = via("SIP/2.0/UDP 192.0.2.1:5070;branch=z9hG4bK.a;rport");
+        assert_eq!(empty_rport.response_addr(), Some("192.0.2.1:5070".parse().unwrap()));
+    }
+
+    #[test]
+    fn maddr_and_ttl_are_parsed_but_do_not_redirect_responses() {
+        let parsed = via("SIP/2.0/UDP 192.0.2.1:5060;branch=z9hG4bK.a;maddr=239.255.255.1;ttl=16");
+        assert_eq!(parsed.maddr(), Some("239.255.255.1"));
+        assert_eq!(parsed.ttl(), Some(16));
+        assert_eq!(parsed.response_addr(), Some("192.0.2.1:5060".parse().unwrap()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,256 @@
+// File: src/transport/mod.rs
+
+pub mod tcp;
+pub mod tls;
+pub mod ws;
+
+use crate::transport::tcp::TcpConnections;
+use crate::transport::ws::WsConnections;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+
+/// Akış taşımalarındaki (TCP, TLS, WebSocket) bir bağlantının, gateway çalıştığı sürece benzersiz kimliği.
+pub type ConnectionId = u64;
+
+/// Ağdan alınmış, işlenmeyi bekleyen bir SIP mesajı.
+#[derive(Debug)]
+pub struct Received {
+    pub packet: Vec<u8>,
+    /// Mesajın geldiği adres, taşıma katmanı ve bacak. Yanıtlar Via'daki taşıma adına değil,
+    /// mesajın gerçekten geldiği taşıma katmanına göre gönderilir.
+    pub source: Peer,
+    /// Mesajın okunduğu bağlantı; UDP mesajlarında `None`.
+    pub connection: Option<ConnectionId>,
+}
+
+/// Yeni kabul edilen veya açılan bir bağlantı için kimlik üretir.
+pub(crate) fn next_connection_id() -> ConnectionId {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Çift yığınlı (`[::]`) soketlerden gelen IPv4 eşlemeli IPv6 adreslerini (`::ffff:a.b.c.d`) düz
+/// IPv4 adrese çevirir. Böylece iç servis adresiyle karşılaştırma, bağlantı tabloları ve duyurulan
+/// adresin seçimi, karşı tarafın gerçek adres ailesine göre yapılır.
+pub fn canonical_addr(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+/// SIP mesajlarının taşındığı katman (RFC 3261 §18).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+    /// SIP over WebSocket (RFC 7118).
+    Ws,
+    Wss,
+}
+
+impl Transport {
+    /// Via başlığında kullanılan taşıma adı (`SIP/2.0/UDP`).
+    pub fn via_token(self) -> &'static str {
+        match self {
+            Transport::Udp => "UDP",
+            Transport::Tcp => "TCP",
+            Transport::Tls => "TLS",
+            Transport::Ws => "WS",
+            Transport::Wss => "WSS",
+        }
+    }
+
+    /// Via başlığındaki veya URI'nin `transport` parametresindeki adı çözümler.
+    pub fn from_token(token: &str) -> Option<Self> {
+        [Transport::Udp, Transport::Tcp, Transport::Tls, Transport::Ws, Transport::Wss]
+            .into_iter()
+            .find(|transport| token.eq_ignore_ascii_case(transport.via_token()))
+    }
+
+    /// Bağlantı yönelimli (güvenilir) taşımalarda istek ve yanıtlar yeniden iletilmez (RFC 3261 §17.1.1.2).
+    pub fn is_reliable(self) -> bool {
+        self != Transport::Udp
+    }
+
+    /// Contact URI'lerine yazılan `transport` parametresi; UDP varsayılan olduğu için yazılmaz.
+    pub fn uri_param(self) -> Option<&'static str> {
+        match self {
+            Transport::Udp => None,
+            Transport::Tcp => Some("tcp"),
+            Transport::Tls => Some("tls"),
+            Transport::Ws => Some("ws"),
+            Transport::Wss => Some("wss"),
+        }
+    }
+
+    /// URI'de veya Via'da port yazılmamışsa kullanılan varsayılan port (RFC 3261 §19.1.2, RFC 7118 §5.3).
+    pub fn default_port(self) -> u16 {
+        match self {
+            Transport::Udp | Transport::Tcp => 5060,
+            Transport::Tls => 5061,
+            Transport::Ws => 80,
+            Transport::Wss => 443,
+        }
+    }
+}
+
+/// Gateway'in ağ bacakları: operatörlere bakan dış bacak ve iç servislere bakan iç bacak.
+/// İç bacak ayrı bir UDP soketi olarak yapılandırılmışsa isteklerin yönü, geldikleri bacağa göre belirlenir.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Leg {
+    External,
+    Internal,
+}
+
+/// Bir mesajın gönderileceği karşı taraf: adres, kullanılacak taşıma katmanı ve karşı tarafa bakan bacak.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Peer {
+    pub addr: SocketAddr,
+    pub transport: Transport,
+    pub leg: Leg,
+}
+
+impl Peer {
+    pub fn new(addr: SocketAddr, transport: Transport) -> Self {
+        Peer { addr, transport, leg: Leg::External }
+    }
+
+    pub fn udp(addr: SocketAddr) -> Self {
+        Peer::new(addr, Transport::Udp)
+    }
+
+    /// İç bacaktaki bir servis; iç ağ ile her zaman UDP üzerinden konuşulur.
+    pub fn internal(addr: SocketAddr) -> Self {
+        Peer::udp(addr).on_leg(Leg::Internal)
+    }
+
+    pub fn on_leg(self, leg: Leg) -> Self {
+        Peer { leg, ..self }
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.transport.via_token())
+    }
+}
+
+/// Gateway'in tüm taşıma katmanlarını tek bir gönderim arayüzünün arkasında toplar.
+/// SIP katmanı (handler, işlem ve diyalog katmanları) mesajları yalnızca bu yapı üzerinden gönderir.
+pub struct TransportLayer {
+    udp: Arc<UdpSocket>,
+    /// UDP soketi IPv6 (çift yığınlı olabilir) bir adrese bağlıysa IPv4 hedefler eşlemeli adresle yazılır.
+    udp_is_v6: bool,
+    /// İç bacağın UDP soketi; yapılandırılmamışsa iç servislere de dış soketten gönderilir.
+    internal_udp: Option<Arc<UdpSocket>>,
+    internal_udp_is_v6: bool,
+    tcp: Arc<TcpConnections>,
+    /// TLS yapılandırılmamışsa `None`; bu durumda TLS hedeflerine gönderim hata döndürür.
+    tls: Option<Arc<TcpConnections>>,
+    ws: Arc<WsConnections>,
+    /// WSS dinleyicisi yapılandırılmamışsa `None`.
+    wss: Option<Arc<WsConnections>>,
+}
+
+impl TransportLayer {
+    pub fn new(
+        udp: Arc<UdpSocket>,
+        internal_udp: Option<Arc<UdpSocket>>,
+        tcp: Arc<TcpConnections>,
+        tls: Option<Arc<TcpConnections>>,
+        ws: Arc<WsConnections>,
+        wss: Option<Arc<WsConnections>>,
+    ) -> Self {
+        let udp_is_v6 = is_v6_socket(&udp);
+        let internal_udp_is_v6 = internal_udp.as_deref().is_some_and(is_v6_socket);
+        TransportLayer { udp, udp_is_v6, internal_udp, internal_udp_is_v6, tcp, tls, ws, wss }
+    }
+
+    pub fn udp(&self) -> &Arc<UdpSocket> {
+        &self.udp
+    }
+
+    pub fn internal_udp(&self) -> Option<&Arc<UdpSocket>> {
+        self.internal_udp.as_ref()
+    }
+
+    pub fn tcp(&self) -> &Arc<TcpConnections> {
+        &self.tcp
+    }
+
+    pub fn tls(&self) -> Option<&Arc<TcpConnections>> {
+        self.tls.as_ref()
+    }
+
+    pub fn ws(&self) -> &Arc<WsConnections> {
+        &self.ws
+    }
+
+    pub fn wss(&self) -> Option<&Arc<WsConnections>> {
+        self.wss.as_ref()
+    }
+
+    /// TLS ile gönderilecek adresin sertifikasının, adresin çözüldüğü alan adına göre doğrulanmasını sağlar.
+    pub fn remember_tls_server_name(&self, addr: SocketAddr, host: &str) {
+        if let Some(tls) = self.tls.as_ref().and_then(|connections| connections.tls()) {
+            tls.remember_server_name(addr, host);
+        }
+    }
+
+    /// Mesajı karşı tarafa, karşı tarafın taşıma katmanı üzerinden gönderir. TCP ve TLS'te varsa
+    /// aynı adrese açık bağlantı yeniden kullanılır, yoksa arka planda yeni bir bağlantı açılır;
+    /// akış taşımalarında gönderim bağlantının kurulmasını beklemez. WebSocket
+    /// istemcilerine yalnızca istemcinin açtığı bağlantıdan gönderilebilir. İç bacaktaki karşı
+    /// taraflara, yapılandırılmışsa iç bacağın soketinden gönderilir.
+    pub async fn send(&self, packet: &[u8], peer: Peer) -> io::Result<()> {
+        match peer.transport {
+            Transport::Udp => match (&self.internal_udp, peer.leg) {
+                (Some(internal), Leg::Internal) => {
+                    internal.send_to(packet, udp_target(self.internal_udp_is_v6, peer.addr)).await.map(|_| ())
+                }
+                _ => self.udp.send_to(packet, udp_target(self.udp_is_v6, peer.addr)).await.map(|_| ()),
+            },
+            Transport::Tcp => self.tcp.send(packet, peer.addr),
+            Transport::Tls => match &self.tls {
+                Some(tls) => tls.send(packet, peer.addr),
+                None => Err(io::Error::new(io::ErrorKind::Unsupported, "TLS taşıması yapılandırılmamış")),
+            },
+            Transport::Ws => self.ws.send(packet, peer.addr),
+            Transport::Wss => match &self.wss {
+                Some(wss) => wss.send(packet, peer.addr),
+                None => Err(io::Error::new(io::ErrorKind::Unsupported, "WSS taşıması yapılandırılmamış")),
+            },
+        }
+    }
+
+    /// Mesajı, verilmişse karşı tarafın `connection` bağlantısından gönderir (örn. diyaloğa bağlı
+    /// bağlantı). Bağlantı kapanmışsa TCP ve TLS'te yeni bağlantı açılabilir; WebSocket'te mesaj
+    /// gönderilemez. `connection` verilmezse veya taşıma UDP ise `send` ile aynıdır.
+    pub async fn send_on(&self, packet: &[u8], peer: Peer, connection: Option<ConnectionId>) -> io::Result<()> {
+        let Some(id) = connection else {
+            return self.send(packet, peer).await;
+        };
+        match (peer.transport, &self.tls, &self.wss) {
+            (Transport::Tcp, _, _) => self.tcp.send_on(packet, peer.addr, id),
+            (Transport::Tls, Some(tls), _) => tls.send_on(packet, peer.addr, id),
+            (Transport::Ws, _, _) => self.ws.send_on(packet, peer.addr, id),
+            (Transport::Wss, _, Some(wss)) => wss.send_on(packet, peer.addr, id),
+            _ => self.send(packet, peer).await,
+        }
+    }
+}
+
+fn is_v6_socket(socket: &UdpSocket) -> bool {
+    socket.local_addr().is_ok_and(|addr| addr.is_ipv6())
+}
+
+/// IPv6 soketten gönderilen IPv4 hedefleri eşlemeli adrese çevirir.
+fn udp_target(socket_is_v6: bool, addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if socket_is_v6 => SocketAddr::new(IpAddr::V6(ip.to_ipv6_mapped()), addr.port()),
+        _ => addr,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,453 @@
+// File: src/transport/tcp.rs
+
+use crate::transport::tls::TlsContext;
+use crate::transport::{canonical_addr, next_connection_id, ConnectionId, Peer, Received, Transport};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info, warn};
+
+/// Akıştan okunabilecek tek bir SIP mesajının en büyük boyutu (başlıklar ve gövde dahil).
+pub(crate) const MAX_MESSAGE_SIZE: usize = 65535;
+/// Bir bağlantıya yazılmayı bekleyebilecek en fazla mesaj.
+pub(crate) const WRITE_QUEUE_DEPTH: usize = 256;
+/// Karşı tarafa yeni bağlantı açarken (TLS el sıkışması dahil) beklenecek en uzun süre.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Gelen bir bağlantının TLS el sıkışmasını tamamlaması için beklenecek en uzun süre.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Açık bir akış bağlantısının kimliği ve yazma kuyruğu.
+pub(crate) type ConnectionWriter = (ConnectionId, mpsc::Sender<Vec<u8>>);
+
+/// Akış bağlantılarına (TCP, TLS, WebSocket) uygulanan sınırlar. Tüm dinleyiciler aynı
+/// bağlantı kotasını paylaşır.
+#[derive(Clone)]
+pub struct StreamLimits {
+    /// Bu süre boyunca hiç veri okunmayan bağlantı kapatılır. CRLF keep-alive'ları da veri sayılır.
+    pub idle_timeout: Duration,
+    /// Gelen bağlantıların kotası; kota dolduğunda yeni bağlantılar kabul edilip hemen kapatılır.
+    accepted: Arc<Semaphore>,
+}
+
+impl StreamLimits {
+    pub fn new(idle_timeout: Duration, max_connections: usize) -> Self {
+        StreamLimits { idle_timeout, accepted: Arc::new(Semaphore::new(max_connections)) }
+    }
+
+    /// Gelen bir bağlantı için kotadan yer ayırır; bağlantı kapanana kadar tutulmalıdır.
+    /// Kota doluysa `None` döner.
+    pub(crate) fn admit(&self) -> Option<OwnedSemaphorePermit> {
+        self.accepted.clone().try_acquire_owned().ok()
+    }
+}
+
+/// Açık TCP bağlantılarını karşı tarafın adresine göre tutar. Bir adrese gönderilecek
+/// mesajlar (örn. aynı bağlantıdan gelen isteğin yanıtı) o adrese açık bağlantıdan gönderilir
+/// (RFC 3261 §18.2.2); bağlantı yoksa gateway arka planda yeni bir bağlantı açar.
+/// TLS yapılandırması verilmişse bağlantılar TLS ile şifrelenir; TCP ve TLS bağlantıları ayrı tablolarda tutulur.
+pub struct TcpConnections {
+    /// Her adrese açık bağlantının kimliği ve yazma kuyruğu.
+    connections: Mutex<HashMap<SocketAddr, ConnectionWriter>>,
+    /// Bağlantılardan okunan mesajların ağ katmanına teslim edildiği kanal.
+    inbound: mpsc::Sender<Received>,
+    tls: Option<Arc<TlsContext>>,
+    limits: StreamLimits,
+}
+
+impl TcpConnections {
+    pub fn new(inbound: mpsc::Sender<Received>, tls: Option<Arc<TlsContext>>, limits: StreamLimits) -> Self {
+        TcpConnections { connections: Mutex::new(HashMap::new()), inbound, tls, limits }
+    }
+
+    /// Bu tablodaki bağlantıların taşıma katmanı.
+    pub fn transport(&self) -> Transport {
+        if self.tls.is_some() {
+            Transport::Tls
+        } else {
+            Transport::Tcp
+        }
+    }
+
+    pub fn tls(&self) -> Option<&Arc<TlsContext>> {
+        self.tls.as_ref()
+    }
+
+    /// Gelen bağlantıları kabul eder; her bağlantı, kapanana kadar kayıtlı kalır. Gelen bağlantı
+    /// kotası doluysa bağlantı hemen kapatılır.
+    /// TLS el sıkışması ayrı bir görevde yapılır; yavaş bir istemci diğer bağlantıları bekletmez.
+    pub async fn accept_loop(self: Arc<Self>, listener: TcpListener) {
+        let transport = self.transport();
+        loop {
+            match listener.accept().await {
+                Ok((stream, remote_addr)) => {
+                    let remote_addr = canonical_addr(remote_addr);
+                    let Some(permit) = self.limits.admit() else {
+                        warn!(source = %remote_addr, transport = transport.via_token(), "Gelen bağlantı sınırına ulaşıldı, bağlantı kapatıldı.");
+                        continue;
+                    };
+                    debug!(source = %remote_addr, transport = transport.via_token(), "Yeni bağlantı kabul edildi.");
+                    let _ = stream.set_nodelay(true);
+                    match self.tls.clone() {
+                        None => {
+                            self.register(stream, remote_addr, permit);
+                        }
+                        Some(tls) => {
+                            let connections = self.clone();
+                            tokio::spawn(async move {
+                                match tokio::time::timeout(HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                                    Ok(Ok(stream)) => {
+                                        connections.register(stream, remote_addr, permit);
+                                    }
+                                    Ok(Err(e)) => warn!(source = %remote_addr, error = %e, "TLS el sıkışması başarısız, bağlantı kapatıldı."),
+                                    Err(_) => warn!(source = %remote_addr, "TLS el sıkışması zaman aşımına uğradı, bağlantı kapatıldı."),
+                                }
+                            });
+                        }
+                    }
+                }
+                Err(e) => {
+                    // Dosya tanıtıcısı tükenmesi gibi hatalarda döngünün boşa dönmemesi için kısa bir bekleme yapılır.
+                    warn!(error = %e, transport = transport.via_token(), "Bağlantı kabul edilemedi.");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+
+    /// Mesajı adrese açık bağlantının yazma kuyruğuna ekler. Bağlantı yoksa yenisi arka planda
+    /// açılır ve mesaj, bağlantı kurulana kadar kuyrukta bekler. Gönderim hiçbir zaman bağlantının
+    /// kurulmasını veya kuyruğun boşalmasını beklemez; yavaş ya da ulaşılamayan bir karşı taraf
+    /// böylece zamanlayıcıları ve işçi kuyruklarını durdurmaz.
+    pub fn send(self: &Arc<Self>, packet: &[u8], addr: SocketAddr) -> io::Result<()> {
+        let writer = {
+            let mut connections = self.connections.lock().unwrap();
+            match connections.get(&addr) {
+                Some((_, writer)) => writer.clone(),
+                None => {
+                    let id = next_connection_id();
+                    let (tx, rx) = mpsc::channel(WRITE_QUEUE_DEPTH);
+                    connections.insert(addr, (id, tx.clone()));
+                    tokio::spawn(self.clone().connect(addr, id, tx.clone(), rx));
+                    tx
+                }
+            }
+        };
+        queue_packet(&writer, packet)
+    }
+
+    /// Mesajı, adrese hâlâ `id` bağlantısı açıksa o bağlantıdan gönderir. Bağlantı kapanmışsa
+    /// istek, `send` ile adrese açık başka bir bağlantıdan veya yeni bir bağlantıdan gönderilir
+    /// (RFC 3261 §18.1.1).
+    pub fn send_on(self: &Arc<Self>, packet: &[u8], addr: SocketAddr, id: ConnectionId) -> io::Result<()> {
+        let writer = self
+            .connections
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .filter(|(current, _)| *current == id)
+            .map(|(_, writer)| writer.clone());
+        match writer {
+            Some(writer) => queue_packet(&writer, packet),
+            None => {
+                debug!(target_addr = %addr, connection = id, "Diyaloğun bağlantısı kapanmış, mesaj başka bir bağlantıdan gönderiliyor.");
+                self.send(packet, addr)
+            }
+        }
+    }
+
+    /// Karşı tarafa bağlantı açar ve kuyrukta bekleyen mesajları yazmaya başlar. Bağlantı
+    /// kurulamazsa kayıt silinir ve bekleyen mesajlar atılır; bir sonraki gönderim yeniden dener.
+    async fn connect(self: Arc<Self>, addr: SocketAddr, id: ConnectionId, tx: mpsc::Sender<Vec<u8>>, rx: mpsc::Receiver<Vec<u8>>) {
+        let connecting = async {
+            let stream = TcpStream::connect(addr).await?;
+            let _ = stream.set_nodelay(true);
+            match &self.tls {
+                None => self.start(stream, addr, id, tx.clone(), rx, None),
+                Some(tls) => self.start(tls.connect(stream, addr).await?, addr, id, tx.clone(), rx, None),
+            }
+            Ok::<_, io::Error>(())
+        };
+        let transport = self.transport().via_token();
+        match tokio::time::timeout(CONNECT_TIMEOUT, connecting).await {
+            Ok(Ok(())) => {
+                info!(target_addr = %addr, transport, "Karşı tarafa yeni bağlantı açıldı.");
+                return;
+            }
+            Ok(Err(e)) => warn!(target_addr = %addr, transport, error = %e, "Karşı tarafa bağlantı açılamadı, bekleyen mesajlar atıldı."),
+            Err(_) => warn!(target_addr = %addr, transport, "Bağlantı zaman aşımına uğradı, bekleyen mesajlar atıldı."),
+        }
+        self.forget(addr, &tx);
+    }
+
+    /// Gelen bir bağlantıyı kaydeder ve okuma ve yazma görevlerini başlatır.
+    fn register<S>(self: &Arc<Self>, stream: S, addr: SocketAddr, permit: OwnedSemaphorePermit)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let id = next_connection_id();
+        let (tx, rx) = mpsc::channel(WRITE_QUEUE_DEPTH);
+        self.connections.lock().unwrap().insert(addr, (id, tx.clone()));
+        self.start(stream, addr, id, tx, rx, Some(permit));
+    }
+
+    /// Kayıtlı bir bağlantının okuma ve yazma görevlerini başlatır. Gelen bağlantıların kota
+    /// payı (`permit`) bağlantı kapanana kadar okuma görevinde tutulur.
+    fn start<S>(
+        self: &Arc<Self>,
+        stream: S,
+        addr: SocketAddr,
+        id: ConnectionId,
+        tx: mpsc::Sender<Vec<u8>>,
+        rx: mpsc::Receiver<Vec<u8>>,
+        permit: Option<OwnedSemaphorePermit>,
+    ) where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        tokio::spawn(write_loop(writer, rx, addr));
+        tokio::spawn(self.clone().read_loop(reader, addr, id, tx, permit));
+    }
+
+    /// Bağlantıyı, hâlâ aynı yazma kanalıyla kayıtlıysa kayıttan siler.
+    fn forget(&self, addr: SocketAddr, writer: &mpsc::Sender<Vec<u8>>) {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.get(&addr).is_some_and(|(_, current)| current.same_channel(writer)) {
+            connections.remove(&addr);
+        }
+    }
+
+    /// Akışı mesajlara böler ve ağ katmanına teslim eder. Bağlantı kapandığında, boşta kalma
+    /// süresi dolduğunda veya çerçeveleme hatasında bağlantı kayıttan silinir; yazma görevi de
+    /// böylece sonlanır.
+    async fn read_loop(
+        self: Arc<Self>,
+        mut reader: impl AsyncRead + Unpin,
+        addr: SocketAddr,
+        id: ConnectionId,
+        writer: mpsc::Sender<Vec<u8>>,
+        _permit: Option<OwnedSemaphorePermit>,
+    ) {
+        let source = Peer::new(addr, self.transport());
+        let connection = Some(id);
+        let mut framer = StreamFramer::default();
+        let mut buf = vec![0u8; 16384];
+        'read: loop {
+            let len = match tokio::time::timeout(self.limits.idle_timeout, reader.read(&mut buf)).await {
+                Ok(Ok(0)) => break,
+                Ok(Ok(len)) => len,
+                Ok(Err(e)) => {
+                    debug!(source = %addr, error = %e, "TCP bağlantısından okuma hatası.");
+                    break;
+                }
+                Err(_) => {
+                    debug!(source = %addr, "TCP bağlantısı boşta kalma süresini aştı, kapatılıyor.");
+                    break;
+                }
+            };
+            framer.push(&buf[..len]);
+            loop {
+                match framer.next_frame() {
+                    Ok(Some(Frame::Message(message))) => {
+                        if self.inbound.send(Received { packet: message, source, connection }).await.is_err() {
+                            break 'read;
+                        }
+                    }
+                    // RFC 5626 §3.5.1: çift CRLF "ping" mesajına tek CRLF "pong" ile yanıt verilir.
+                    Ok(Some(Frame::Ping)) => {
+                        let _ = writer.send(b"\r\n".to_vec()).await;
+                    }
+                    Ok(None) => break,
+                    Err(reason) => {
+                        warn!(source = %addr, reason, "TCP akışı SIP mesajlarına bölünemedi, bağlantı kapatılıyor.");
+                        break 'read;
+                    }
+                }
+            }
+        }
+        self.forget(addr, &writer);
+        debug!(source = %addr, "TCP bağlantısı kapandı.");
+    }
+}
+
+/// Mesajı bir bağlantının yazma kuyruğuna, beklemeden ekler. Kuyruk doluysa mesaj atılır;
+/// UDP'deki gibi yeniden iletim veya işlem zaman aşımı durumu toparlar.
+pub(crate) fn queue_packet(writer: &mpsc::Sender<Vec<u8>>, packet: &[u8]) -> io::Result<()> {
+    writer.try_send(packet.to_vec()).map_err(|e| match e {
+        TrySendError::Full(_) => io::Error::new(ErrorKind::WouldBlock, "bağlantının yazma kuyruğu dolu"),
+        TrySendError::Closed(_) => io::Error::new(ErrorKind::BrokenPipe, "bağlantı kapandı"),
+    })
+}
+
+async fn write_loop(mut writer: impl AsyncWrite + Unpin, mut rx: mpsc::Receiver<Vec<u8>>, addr: SocketAddr) {
+    while let Some(packet) = rx.recv().await {
+        if let Err(e) = writer.write_all(&packet).await {
+            warn!(target_addr = %addr, error = %e, "TCP bağlantısına yazılamadı.");
+            break;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+/// Akıştan çıkarılan bir birim: tam bir SIP mesajı veya keep-alive ping'i.
+#[derive(Debug, PartialEq, Eq)]
+enum Frame {
+    Message(Vec<u8>),
+    Ping,
+}
+
+/// Bayt akışını `Content-Length` başlığına göre SIP mesajlarına böler (RFC 3261 §18.3).
+#[derive(Default)]
+struct StreamFramer {
+    buf: Vec<u8>,
+}
+
+impl StreamFramer {
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>, &'static str> {
+        // Mesajlar arasındaki boş satırlar yok sayılır (RFC 3261 §7.5); çift CRLF ise ping'dir.
+        while self.buf.starts_with(b"\r\n") {
+            if self.buf.starts_with(b"\r\n\r\n") {
+                self.buf.drain(..4);
+                return Ok(Some(Frame::Ping));
+            }
+            if self.buf.len() < 4 {
+                return Ok(None);
+            }
+            self.buf.drain(..2);
+        }
+        let Some(header_len) = self.buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.buf.len() > MAX_MESSAGE_SIZE {
+                return Err("başlık bölümü çok büyük");
+            }
+            return Ok(None);
+        };
+        let header_end = header_len + 4;
+        let content_length = content_length(&self.buf[..header_len]).ok_or("geçersiz Content-Length")?;
+        let total = header_end + content_length;
+        if total > MAX_MESSAGE_SIZE {
+            return Err("mesaj çok büyük");
+        }
+        if self.buf.len() < total {
+            return Ok(None);
+        }
+        let message = self.buf.drain(..total).collect();
+        Ok(Some(Frame::Message(message)))
+    }
+}
+
+/// Başlık bölümündeki `Content-Length` (veya kısa formu `l`) değerini döndürür.
+/// Akış taşımalarında başlık zorunludur; yazılmamışsa gövde olmadığı varsayılır.
+fn content_length(headers: &[u8]) -> Option<usize> {
+    for line in headers.split(|&b| b == b'\n').skip(1) {
+        let Some(colon) = line.iter().position(|&b| b == b':') else {
+            continue;
+        };
+        let name = line[..colon].trim_ascii();
+        if name.eq_ignore_ascii_case(b"Content-Length") || name.eq_ignore_ascii_case(b"l") {
+            return std::str::from_utf8(line[colon + 1..].trim_ascii()).ok()?.parse().ok();
+        }
+    }
+    Some(0)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &[u8] = b"OPTIONS sip:gw@example.com SIP/2.0\r\n\
+Via: SIP/2.0/TCP 192.0.2.1:5060;branch=z9hG4bK1\r\n\
+Call-ID: framer\r\n\
+CSeq: 1 OPTIONS\r\n\
+Content-Length: 4\r\n\r\n\
+abcd";
+
+    fn frames(framer: &mut StreamFramer) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        while let Some(frame) = framer.next_frame().unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn partial_reads_wait_for_the_whole_message() {
+        let mut framer = StreamFramer::default();
+        for split in [10, OPTIONS.len() - 2] {
+            framer.push(&OPTIONS[..split]);
+            assert_eq!(framer.next_frame(), Ok(None));
+            framer.push(&OPTIONS[split..]);
+            assert_eq!(frames(&mut framer), vec![Frame::Message(OPTIONS.to_vec())]);
+        }
+    }
+
+    #[test]
+    fn several_messages_in_one_read_are_split() {
+        let mut framer = StreamFramer::default();
+        let mut data = OPTIONS.to_vec();
+        data.extend_from_slice(OPTIONS);
+        data.extend_from_slice(&OPTIONS[..20]);
+        framer.push(&data);
+        assert_eq!(frames(&mut framer), vec![Frame::Message(OPTIONS.to_vec()), Frame::Message(OPTIONS.to_vec())]);
+        framer.push(&OPTIONS[20..]);
+        assert_eq!(frames(&mut framer), vec![Frame::Message(OPTIONS.to_vec())]);
+    }
+
+    #[test]
+    fn crlf_keep_alives_are_pings_and_single_crlf_is_skipped() {
+        let mut framer = StreamFramer::default();
+        framer.push(b"\r\n\r\n");
+        assert_eq!(frames(&mut framer), vec![Frame::Ping]);
+
+        framer.push(b"\r\n");
+        assert_eq!(framer.next_frame(), Ok(None));
+        framer.push(OPTIONS);
+        assert_eq!(frames(&mut framer), vec![Frame::Message(OPTIONS.to_vec())]);
+    }
+
+    #[test]
+    fn missing_content_length_means_empty_body() {
+        let message = b"OPTIONS sip:gw@example.com SIP/2.0\r\nCall-ID: framer\r\nCSeq: 1 OPTIONS\r\n\r\n";
+        let mut framer = StreamFramer::default();
+        framer.push(message);
+        framer.push(OPTIONS);
+        assert_eq!(frames(&mut framer), vec![Frame::Message(message.to_vec()), Frame::Message(OPTIONS.to_vec())]);
+    }
+
+    #[test]
+    fn compact_content_length_is_honoured() {
+        let message = b"OPTIONS sip:gw@example.com SIP/2.0\r\nl: 2\r\n\r\nok";
+        let mut framer = StreamFramer::default();
+        framer.push(message);
+        assert_eq!(frames(&mut framer), vec![Frame::Message(message.to_vec())]);
+    }
+
+    #[test]
+    fn oversized_content_length_is_rejected() {
+        let message = format!("OPTIONS sip:gw@example.com SIP/2.0\r\nContent-Length: {}\r\n\r\n", MAX_MESSAGE_SIZE);
+        let mut framer = StreamFramer::default();
+        framer.push(message.as_bytes());
+        assert_eq!(framer.next_frame(), Err("mesaj çok büyük"));
+    }
+
+    #[test]
+    fn invalid_content_length_is_rejected() {
+        let mut framer = StreamFramer::default();
+        framer.push(b"OPTIONS sip:gw@example.com SIP/2.0\r\nContent-Length: -1\r\n\r\n");
+        assert_eq!(framer.next_frame(), Err("geçersiz Content-Length"));
+    }
+
+    #[test]
+    fn endless_header_section_is_rejected() {
+        let mut framer = StreamFramer::default();
+        framer.push(&vec![b'a'; MAX_MESSAGE_SIZE + 1]);
+        assert_eq!(framer.next_frame(), Err("başlık bölümü çok büyük"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,234 @@
+// File: src/transport/tls.rs
+
+use crate::config::AppConfig;
+use crate::error::GatewayError;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+
+/// Sertifika doğrulaması için sunucu adı saklanan en fazla adres; dolduğunda en eski kayıt silinir.
+const MAX_SERVER_NAMES: usize = 1024;
+
+/// Gelen TLS bağlantılarında istemci sertifikasının istenip istenmeyeceği.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsClientAuth {
+    /// İstemci sertifikası istenmez.
+    None,
+    /// Sertifika istenir; gönderilmişse CA paketine göre doğrulanır, gönderilmemişse bağlantı kabul edilir.
+    Optional,
+    /// Sertifika zorunludur ve CA paketine göre doğrulanır.
+    Required,
+}
+
+impl TlsClientAuth {
+    /// `SIP_GATEWAY_TLS_CLIENT_AUTH` değerini (`none`, `optional`, `required`) çözümler.
+    pub fn from_env_value(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Some(TlsClientAuth::None),
+            "optional" => Some(TlsClientAuth::Optional),
+            "required" => Some(TlsClientAuth::Required),
+            _ => None,
+        }
+    }
+}
+
+/// TLS el sıkışmaları için sunucu ve istemci yapılandırması.
+/// Sunucu tarafı, sertifika ve anahtar dosyaları verildiğinde; istemci tarafı (operatöre giden
+/// bağlantılar), karşı tarafın sertifikasını doğrulayacak CA paketi verildiğinde etkinleşir.
+pub struct TlsContext {
+    acceptor: Option<TlsAcceptor>,
+    connector: Option<TlsConnector>,
+    /// Giden bağlantılarda sertifikası doğrulanacak sunucu adları; hedef adresi alan adından çözülen istekler için tutulur.
+    server_names: Mutex<ServerNames>,
+}
+
+/// Adreslere göre sunucu adları, eklenme sırasıyla. Kayıt sayısı `MAX_SERVER_NAMES` ile sınırlıdır.
+#[derive(Default)]
+struct ServerNames {
+    names: HashMap<SocketAddr, ServerName<'static>>,
+    order: VecDeque<SocketAddr>,
+}
+
+impl ServerNames {
+    /// Adresin sunucu adını kaydeder veya günceller; sınır aşılırsa en eski kayıt silinir.
+    fn insert(&mut self, addr: SocketAddr, name: ServerName<'static>) {
+        if self.names.insert(addr, name).is_none() {
+            self.order.push_back(addr);
+            if self.order.len() > MAX_SERVER_NAMES {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.names.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    fn get(&self, addr: &SocketAddr) -> Option<&ServerName<'static>> {
+        self.names.get(addr)
+    }
+}
+
+impl TlsContext {
+    /// Yapılandırmadaki sertifika, anahtar ve CA dosyalarını yükler.
+    /// Hiçbiri verilmemişse TLS kapalıdır ve `None` döner.
+    pub fn load(config: &AppConfig) -> Result<Option<Self>, GatewayError> {
+        let identity = match (&config.tls_cert_file, &config.tls_key_file) {
+            (Some(cert_file), Some(key_file)) => Some((load_certs(cert_file)?, load_key(key_file)?)),
+            (None, None) => None,
+            _ => {
+                return Err(GatewayError::TlsConfig(
+                    "SIP_GATEWAY_TLS_CERT_FILE ve SIP_GATEWAY_TLS_KEY_FILE birlikte verilmelidir".to_string(),
+                ))
+            }
+        };
+        let roots = match &config.tls_ca_file {
+            Some(ca_file) => Some(Arc::new(load_roots(ca_file)?)),
+            None => None,
+        };
+        if identity.is_none() && roots.is_none() {
+            return Ok(None);
+        }
+
+        let acceptor = match &identity {
+            Some((certs, key)) => {
+                let builder = ServerConfig::builder();
+                let builder = match (config.tls_client_auth, &roots) {
+                    (TlsClientAuth::None, _) => builder.with_no_client_auth(),
+                    (_, None) => {
+                        return Err(GatewayError::TlsConfig(
+                            "istemci sertifikası doğrulaması için SIP_GATEWAY_TLS_CA_FILE gereklidir".to_string(),
+                        ))
+                    }
+                    (client_auth, Some(roots)) => {
+                        let verifier = WebPkiClientVerifier::builder(roots.clone());
+                        let verifier = if client_auth == TlsClientAuth::Optional { verifier.allow_unauthenticated() } else { verifier };
+                        let verifier = verifier.build().map_err(|e| GatewayError::TlsConfig(e.to_string()))?;
+                        builder.with_client_cert_verifier(verifier)
+                    }
+                };
+                let server_config = builder
+                    .with_single_cert(certs.clone(), key.clone_key())
+                    .map_err(|e| GatewayError::TlsConfig(format!("sunucu sertifikası kullanılamıyor: {}", e)))?;
+                Some(TlsAcceptor::from(Arc::new(server_config)))
+            }
+            None => None,
+        };
+
+        // Karşı taraf istemci sertifikası isterse (karşılıklı TLS) gateway'in kendi sertifikası sunulur.
+        let connector = match roots {
+            Some(roots) => {
+                let builder = ClientConfig::builder().with_root_certificates(roots);
+                let client_config = match identity {
+                    Some((certs, key)) => builder
+                        .with_client_auth_cert(certs, key)
+                        .map_err(|e| GatewayError::TlsConfig(format!("istemci sertifikası kullanılamıyor: {}", e)))?,
+                    None => builder.with_no_client_auth(),
+                };
+                Some(TlsConnector::from(Arc::new(client_config)))
+            }
+            None => None,
+        };
+
+        Ok(Some(TlsContext { acceptor, connector, server_names: Mutex::default() }))
+    }
+
+    /// Gelen TLS bağlantılarının kabul edilip edilemeyeceği (sunucu sertifikası yüklenmiş mi).
+    pub fn can_accept(&self) -> bool {
+        self.acceptor.is_some()
+    }
+
+    /// Adrese açılacak bağlantıda sertifikanın hangi alan adına göre doğrulanacağını kaydeder.
+    /// Kayıt olmayan adreslerde sertifika IP adresine göre doğrulanır. En fazla `MAX_SERVER_NAMES`
+    /// adres tutulur; daha eski kayıtların adresine yeni açılan bağlantılar IP adresine göre doğrulanır.
+    pub fn remember_server_name(&self, addr: SocketAddr, host: &str) {
+        if let Ok(name) = ServerName::try_from(host.to_string()) {
+            self.server_names.lock().unwrap().insert(addr, name);
+        }
+    }
+
+    /// Gelen bağlantıda sunucu tarafı el sıkışmasını yapar.
+    pub async fn accept(&self, stream: TcpStream) -> io::Result<TlsStream<TcpStream>> {
+        let acceptor = self
+            .acceptor
+            .as_ref()
+            .ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "TLS sunucu sertifikası yapılandırılmamış"))?;
+        Ok(acceptor.accept(stream).await?.into())
+    }
+
+    /// Karşı tarafa açılmış bağlantıda istemci tarafı el sıkışmasını yapar ve sertifikayı doğrular.
+    pub async fn connect(&self, stream: TcpStream, addr: SocketAddr) -> io::Result<TlsStream<TcpStream>> {
+        let connector = self
+            .connector
+            .as_ref()
+            .ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "giden TLS için SIP_GATEWAY_TLS_CA_FILE yapılandırılmamış"))?;
+        let name = self
+            .server_names
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .cloned()
+            .unwrap_or_else(|| ServerName::IpAddress(addr.ip().into()));
+        Ok(connector.connect(name, stream).await?.into())
+    }
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, GatewayError> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| GatewayError::TlsConfig(format!("'{}' sertifika dosyası okunamadı: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(GatewayError::TlsConfig(format!("'{}' dosyasında sertifika bulunamadı", path)));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, GatewayError> {
+    PrivateKeyDer::from_pem_file(path).map_err(|e| GatewayError::TlsConfig(format!("'{}' anahtar dosyası okunamadı: {}", path, e)))
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore, GatewayError> {
+    let mut roots = RootCertStore::empty();
+    let (added, _) = roots.add_parsable_certificates(load_certs(path)?);
+    if added == 0 {
+        return Err(GatewayError::TlsConfig(format!("'{}' CA paketinde geçerli sertifika yok", path)));
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(index: usize) -> SocketAddr {
+        SocketAddr::from(([192, 0, 2, 1], 5000 + index as u16))
+    }
+
+    fn name(host: &str) -> ServerName<'static> {
+        ServerName::try_from(host.to_string()).unwrap()
+    }
+
+    #[test]
+    fn server_names_are_capped_and_oldest_is_evicted() {
+        let mut names = ServerNames::default();
+        for index in 0..MAX_SERVER_NAMES {
+            names.insert(addr(index), name("carrier.example.com"));
+        }
+        // Var olan adresin güncellenmesi yeni kayıt sayılmaz.
+        names.insert(addr(0), name("sbc.example.com"));
+        assert_eq!(names.names.len(), MAX_SERVER_NAMES);
+        assert_eq!(names.get(&addr(0)), Some(&name("sbc.example.com")));
+
+        names.insert(addr(MAX_SERVER_NAMES), name("new.example.com"));
+        assert_eq!(names.names.len(), MAX_SERVER_NAMES);
+        assert_eq!(names.order.len(), MAX_SERVER_NAMES);
+        assert!(names.get(&addr(0)).is_none());
+        assert!(names.get(&addr(1)).is_some());
+        assert_eq!(names.get(&addr(MAX_SERVER_NAMES)), Some(&name("new.example.com")));
+    }
+}
\ No newline at end of file
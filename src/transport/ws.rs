@@ -0,0 +1,220 @@
+// File: src/transport/ws.rs
+
+use crate::transport::tcp::{queue_packet, ConnectionWriter, StreamLimits, HANDSHAKE_TIMEOUT, MAX_MESSAGE_SIZE, WRITE_QUEUE_DEPTH};
+use crate::transport::tls::TlsContext;
+use crate::transport::{canonical_addr, next_connection_id, ConnectionId, Peer, Received, Transport};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, OwnedSemaphorePermit};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tracing::{debug, warn};
+
+/// RFC 7118 §4: SIP taşıyan WebSocket bağlantılarında anlaşılan alt protokol.
+const SIP_SUBPROTOCOL: &str = "sip";
+
+/// Tarayıcı tabanlı istemcilerden (WebRTC softphone'lar) gelen WebSocket bağlantılarını
+/// karşı tarafın adresine göre tutar. Her WebSocket mesajı tek bir SIP mesajı taşır (RFC 7118 §5).
+/// İstemciler gelen bağlantıları kabul edemediği için gateway yeni bağlantı açmaz; bir istemciye
+/// giden yanıtlar ve diyalog içi istekler, istemcinin açtığı bağlantıdan gönderilir.
+pub struct WsConnections {
+    /// Her istemci adresine açık bağlantının kimliği ve yazma kuyruğu.
+    connections: Mutex<HashMap<SocketAddr, ConnectionWriter>>,
+    /// Bağlantılardan okunan mesajların ağ katmanına teslim edildiği kanal.
+    inbound: mpsc::Sender<Received>,
+    /// Verilmişse bağlantılar WSS (TLS üzerinden WebSocket) olarak kabul edilir.
+    tls: Option<Arc<TlsContext>>,
+    limits: StreamLimits,
+}
+
+impl WsConnections {
+    pub fn new(inbound: mpsc::Sender<Received>, tls: Option<Arc<TlsContext>>, limits: StreamLimits) -> Self {
+        WsConnections { connections: Mutex::new(HashMap::new()), inbound, tls, limits }
+    }
+
+    /// Bu tablodaki bağlantıların taşıma katmanı.
+    pub fn transport(&self) -> Transport {
+        if self.tls.is_some() {
+            Transport::Wss
+        } else {
+            Transport::Ws
+        }
+    }
+
+    /// Gelen bağlantıları kabul eder. TLS ve WebSocket el sıkışmaları ayrı bir görevde yapılır;
+    /// `sip` alt protokolünü önermeyen istemciler reddedilir. Gelen bağlantı kotası doluysa
+    /// bağlantı hemen kapatılır.
+    pub async fn accept_loop(self: Arc<Self>, listener: TcpListener) {
+        let transport = self.transport();
+        loop {
+            match listener.accept().await {
+                Ok((stream, remote_addr)) => {
+                    let remote_addr = canonical_addr(remote_addr);
+                    let Some(permit) = self.limits.admit() else {
+                        warn!(source = %remote_addr, transport = transport.via_token(), "Gelen bağlantı sınırına ulaşıldı, bağlantı kapatıldı.");
+                        continue;
+                    };
+                    debug!(source = %remote_addr, transport = transport.via_token(), "Yeni bağlantı kabul edildi.");
+                    let _ = stream.set_nodelay(true);
+                    let connections = self.clone();
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(HANDSHAKE_TIMEOUT, connections.handshake(stream, remote_addr, permit)).await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => warn!(source = %remote_addr, error = %e, "WebSocket el sıkışması başarısız, bağlantı kapatıldı."),
+                            Err(_) => warn!(source = %remote_addr, "WebSocket el sıkışması zaman aşımına uğradı, bağlantı kapatıldı."),
+                        }
+                    });
+                }
+                Err(e) => {
+                    // Dosya tanıtıcısı tükenmesi gibi hatalarda döngünün boşa dönmemesi için kısa bir bekleme yapılır.
+                    warn!(error = %e, transport = transport.via_token(), "Bağlantı kabul edilemedi.");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+
+    async fn handshake(self: &Arc<Self>, stream: TcpStream, addr: SocketAddr, permit: OwnedSemaphorePermit) -> io::Result<()> {
+        match &self.tls {
+            None => self.upgrade(stream, addr, permit).await,
+            Some(tls) => self.upgrade(tls.accept(stream).await?, addr, permit).await,
+        }
+    }
+
+    /// HTTP yükseltme isteğini `sip` alt protokolüyle yanıtlar ve bağlantıyı kaydeder.
+    async fn upgrade<S>(self: &Arc<Self>, stream: S, addr: SocketAddr, permit: OwnedSemaphorePermit) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let config = WebSocketConfig::default()
+            .max_message_size(Some(MAX_MESSAGE_SIZE))
+            .max_frame_size(Some(MAX_MESSAGE_SIZE));
+        let ws = tokio_tungstenite::accept_hdr_async_with_config(stream, negotiate_subprotocol, Some(config))
+            .await
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        debug!(source = %addr, transport = self.transport().via_token(), "WebSocket bağlantısı kuruldu.");
+        let (sink, stream) = ws.split();
+        let id = next_connection_id();
+        let (tx, rx) = mpsc::channel(WRITE_QUEUE_DEPTH);
+        self.connections.lock().unwrap().insert(addr, (id, tx.clone()));
+        tokio::spawn(write_loop(sink, rx, addr));
+        tokio::spawn(self.clone().read_loop(stream, addr, id, tx, permit));
+        Ok(())
+    }
+
+    /// Mesajı istemcinin açık bağlantısından gönderir. Bağlantı kapanmışsa istemciye ulaşılamaz.
+    /// Mesaj bağlantının yazma kuyruğuna beklemeden eklenir; kuyruk doluysa atılır.
+    pub fn send(&self, packet: &[u8], addr: SocketAddr) -> io::Result<()> {
+        let writer = self
+            .connections
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .map(|(_, writer)| writer.clone())
+            .ok_or_else(|| io::Error::new(ErrorKind::NotConnected, "istemciye açık WebSocket bağlantısı yok"))?;
+        queue_packet(&writer, packet)
+    }
+
+    /// Mesajı yalnızca `id` bağlantısından gönderir. Bağlantı kapanmışsa, aynı adresten sonradan
+    /// açılmış bir bağlantı başka bir istemciye ait olabileceği için mesaj gönderilmez.
+    pub fn send_on(&self, packet: &[u8], addr: SocketAddr, id: ConnectionId) -> io::Result<()> {
+        let writer = self
+            .connections
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .filter(|(current, _)| *current == id)
+            .map(|(_, writer)| writer.clone())
+            .ok_or_else(|| io::Error::new(ErrorKind::NotConnected, "diyaloğun WebSocket bağlantısı kapanmış"))?;
+        queue_packet(&writer, packet)
+    }
+
+    /// Gelen metin ve ikili mesajları SIP mesajı olarak ağ katmanına teslim eder.
+    /// Ping/pong ve kapanış çerçeveleri WebSocket katmanında yanıtlanır. Boşta kalma süresi
+    /// boyunca hiçbir çerçeve gelmezse bağlantı kapatılır.
+    async fn read_loop(
+        self: Arc<Self>,
+        mut stream: impl Stream<Item = Result<Message, WsError>> + Unpin,
+        addr: SocketAddr,
+        id: ConnectionId,
+        writer: mpsc::Sender<Vec<u8>>,
+        _permit: OwnedSemaphorePermit,
+    ) {
+        let source = Peer::new(addr, self.transport());
+        let connection = Some(id);
+        loop {
+            let message = match tokio::time::timeout(self.limits.idle_timeout, stream.next()).await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(_) => {
+                    debug!(source = %addr, "WebSocket bağlantısı boşta kalma süresini aştı, kapatılıyor.");
+                    break;
+                }
+            };
+            let packet = match message {
+                Ok(Message::Text(text)) => text.as_bytes().to_vec(),
+                Ok(Message::Binary(data)) => data.to_vec(),
+                Ok(Message::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    debug!(source = %addr, error = %e, "WebSocket bağlantısından okuma hatası.");
+                    break;
+                }
+            };
+            if self.inbound.send(Received { packet, source, connection }).await.is_err() {
+                break;
+            }
+        }
+        let mut connections = self.connections.lock().unwrap();
+        if connections.get(&addr).is_some_and(|(_, current)| current.same_channel(&writer)) {
+            connections.remove(&addr);
+        }
+        debug!(source = %addr, "WebSocket bağlantısı kapandı.");
+    }
+}
+
+/// SIP mesajları UTF-8 ise metin, değilse (örn. ikili gövde taşıyorsa) ikili mesaj olarak gönderilir (RFC 7118 §5.1).
+async fn write_loop(
+    mut sink: impl Sink<Message, Error = WsError> + Unpin,
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    addr: SocketAddr,
+) {
+    while let Some(packet) = rx.recv().await {
+        let message = match String::from_utf8(packet) {
+            Ok(text) => Message::text(text),
+            Err(e) => Message::binary(e.into_bytes()),
+        };
+        if let Err(e) = sink.send(message).await {
+            warn!(target_addr = %addr, error = %e, "WebSocket bağlantısına yazılamadı.");
+            break;
+        }
+    }
+    let _ = sink.close().await;
+}
+
+/// İstemci `sip` alt protokolünü önermişse yanıta ekler; önermemişse yükseltmeyi reddeder (RFC 7118 §4).
+#[allow(clippy::result_large_err)] // İmza, tungstenite'in el sıkışma geri çağrısı tarafından belirlenir.
+fn negotiate_subprotocol(request: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+    let offers_sip = request
+        .headers()
+        .get_all("Sec-WebSocket-Protocol")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|protocol| protocol.trim() == SIP_SUBPROTOCOL);
+    if !offers_sip {
+        let mut error = ErrorResponse::new(Some("'sip' alt protokolü gerekli".to_string()));
+        *error.status_mut() = StatusCode::BAD_REQUEST;
+        return Err(error);
+    }
+    response.headers_mut().insert("Sec-WebSocket-Protocol", HeaderValue::from_static(SIP_SUBPROTOCOL));
+    Ok(response)
+}
\ No newline at end of file
@@ -0,0 +1,104 @@
+// File: src/worker.rs
+use crate::config::AppConfig;
+use crate::metrics::{Metrics, METRICS};
+use crate::sip::dialog::Dialogs;
+use crate::sip::handler;
+use crate::sip::message::raw_call_id;
+use crate::sip::transaction::Transactions;
+use crate::transport::{ConnectionId, Peer, TransportLayer};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Semaphore;
+use tracing::{error, warn};
+
+/// Ağdan alınmış, işlenmeyi bekleyen bir paket.
+struct Job {
+    packet: Vec<u8>,
+    /// Paketin geldiği adres, taşıma katmanı ve bacak; iç bacak yapılandırılmışsa bacak isteğin yönünü belirler.
+    source: Peer,
+    connection: Option<ConnectionId>,
+}
+
+/// Paket işleme için gereken, tüm işçilerin paylaştığı durum.
+struct WorkerContext {
+    transport: Arc<TransportLayer>,
+    transactions: Transactions,
+    dialogs: Dialogs,
+    config: Arc<AppConfig>,
+    /// Tüm kuyruklar için eşzamanlı işlenen paket sınırı.
+    limiter: Semaphore,
+}
+
+/// Paketleri Call-ID'ye göre sabit sayıda sıralı kuyruğa dağıtır.
+/// Aynı çağrının paketleri (örn. 200 OK ve ardından gelen BYE) hep aynı kuyruğa düşer
+/// ve geliş sırasıyla işlenir. Kuyruklar sınırlıdır; dolu bir kuyruğa gelen paket atılır
+/// ve UDP üzerindeki karşı tarafın yeniden iletimine bırakılır.
+pub struct WorkerPool {
+    queues: Vec<mpsc::Sender<Job>>,
+    hasher: RandomState,
+}
+
+impl WorkerPool {
+    pub fn spawn(transport: Arc<TransportLayer>, transactions: Transactions, dialogs: Dialogs, config: Arc<AppConfig>) -> Self {
+        let context = Arc::new(WorkerContext {
+            transport,
+            transactions,
+            dialogs,
+            limiter: Semaphore::new(config.max_concurrent_packets),
+            config,
+        });
+        let queues = (0..context.config.worker_count)
+            .map(|_| {
+                let (tx, rx) = mpsc::channel(context.config.worker_queue_depth);
+                tokio::spawn(run_worker(rx, context.clone()));
+                tx
+            })
+            .collect();
+        WorkerPool { queues, hasher: RandomState::new() }
+    }
+
+    /// Paketi Call-ID'sinin düştüğü kuyruğa ekler. Call-ID'si bulunamayan paketler
+    /// (zaten reddedilecekleri için) kaynak adrese göre dağıtılır.
+    pub fn dispatch(&self, packet: Vec<u8>, source: Peer, connection: Option<ConnectionId>) {
+        let hash = match raw_call_id(&packet) {
+            Some(call_id) => self.hasher.hash_one(call_id),
+            None => self.hasher.hash_one(source.addr),
+        };
+        let queue = &self.queues[hash as usize % self.queues.len()];
+        match queue.try_send(Job { packet, source, connection }) {
+            Ok(()) => Metrics::increment(&METRICS.packets_queued),
+            Err(TrySendError::Full(_)) => {
+                Metrics::increment(&METRICS.packets_dropped_queue_full);
+                warn!(source = %source, "İşçi kuyruğu dolu, paket atıldı.");
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!(source = %source, "İşçi kuyruğu kapanmış, paket atıldı.");
+            }
+        }
+    }
+}
+
+/// Tek bir kuyruğun paketlerini sırayla işler. Her paket ayrı bir görevde çalıştırılır ve
+/// bitmesi beklenir; böylece sıra korunur, bir paketin işlenirken çökmesi kuyruğu durdurmaz.
+async fn run_worker(mut rx: mpsc::Receiver<Job>, context: Arc<WorkerContext>) {
+    while let Some(job) = rx.recv().await {
+        Metrics::decrement(&METRICS.packets_queued);
+        let Ok(_permit) = context.limiter.acquire().await else {
+            return;
+        };
+        Metrics::increment(&METRICS.packets_in_flight);
+        let ctx = context.clone();
+        let result = tokio::spawn(async move {
+            if let Err(e) = handler::handle_packet(&job.packet, job.source, job.connection, &ctx.transport, &ctx.transactions, &ctx.dialogs, &ctx.config).await {
+                warn!(source = %job.source, error = %e, "SIP paketi işlenemedi.");
+            }
+        })
+        .await;
+        Metrics::decrement(&METRICS.packets_in_flight);
+        if let Err(e) = result {
+            error!(error = %e, "SIP paketi işlenirken görev beklenmedik şekilde sonlandı.");
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,102 @@
+// sentiric-sip-gateway-service/tests/tls.rs
+// TLS dinleyicisinin uçtan uca testi: kendinden imzalı bir sertifikayla 127.0.0.1:0 üzerinde
+// açılan gateway'e tokio-rustls ile bağlanılır, gönderilen OPTIONS sahte sinyal servisine
+// iletilir ve yanıtı aynı TLS bağlantısından beklenir.
+use sentiric_sip_gateway_service::config::AppConfig;
+use sentiric_sip_gateway_service::network;
+use sentiric_sip_gateway_service::sip::{dialog, transaction};
+use std::env;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::timeout;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+const BRANCH: &str = "z9hG4bK-tls-loopback";
+
+#[tokio::test]
+async fn options_over_tls_is_answered_on_the_same_connection() {
+    let certified = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let dir = env::temp_dir().join(format!("sip-gateway-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_file = dir.join("gateway.pem");
+    let key_file = dir.join("gateway.key");
+    std::fs::write(&cert_file, certified.cert.pem()).unwrap();
+    std::fs::write(&key_file, certified.key_pair.serialize_pem()).unwrap();
+
+    // Sinyal servisinin yerine geçen UDP soketi.
+    let signaling = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    // Bu test ikilisindeki tek test olduğu için ortam değişkenleri başka testlerle yarışmaz.
+    env::set_var("SIP_GATEWAY_BIND_ADDR", "127.0.0.1");
+    env::set_var("SIP_GATEWAY_UDP_PORT", "0");
+    env::set_var("SIP_GATEWAY_TLS_PORT", "0");
+    env::set_var("SIP_GATEWAY_WS_PORT", "0");
+    env::set_var("SIP_GATEWAY_TLS_CERT_FILE", &cert_file);
+    env::set_var("SIP_GATEWAY_TLS_KEY_FILE", &key_file);
+    env::set_var("SIP_SIGNALING_TARGET_UDP_URL", signaling.local_addr().unwrap().to_string());
+    env::set_var("SIP_GATEWAY_PUBLIC_IP", "127.0.0.1");
+    let config = Arc::new(AppConfig::load_from_env().unwrap());
+
+    let listeners = network::bind_transport(&config).await.unwrap();
+    let tls_addr = listeners.tls_local_addr().expect("TLS dinleyicisi açılmadı");
+    tokio::spawn(network::listen_and_process(
+        listeners,
+        config.clone(),
+        transaction::new_transaction_manager(),
+        dialog::new_dialog_manager(&config),
+    ));
+
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from(certified.cert.der().to_vec())).unwrap();
+    let connector = TlsConnector::from(Arc::new(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth()));
+    let tcp = TcpStream::connect(tls_addr).await.unwrap();
+    let client_addr = tcp.local_addr().unwrap();
+    let server_name = ServerName::IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST).into());
+    let mut stream = connector.connect(server_name, tcp).await.unwrap();
+
+    let options = format!(
+        "OPTIONS sip:gateway@{tls_addr};transport=tls SIP/2.0\r\n\
+Via: SIP/2.0/TLS {client_addr};branch={BRANCH}\r\n\
+Max-Forwards: 70\r\n\
+From: <sip:carrier@example.com>;tag=tls1\r\n\
+To: <sip:gateway@example.com>\r\n\
+Call-ID: tls-loopback\r\n\
+CSeq: 1 OPTIONS\r\n\
+Content-Length: 0\r\n\r\n"
+    );
+    stream.write_all(options.as_bytes()).await.unwrap();
+
+    // Sinyal servisi isteği alır ve Via listesini koruyarak 200 OK ile yanıtlar.
+    let mut buf = [0; 4096];
+    let (len, gateway_addr) = timeout(Duration::from_secs(5), signaling.recv_from(&mut buf)).await.unwrap().unwrap();
+    let request = String::from_utf8_lossy(&buf[..len]).to_string();
+    assert!(request.starts_with("OPTIONS "), "sinyal servisine iletilen istek: {request}");
+    let mut response = String::from("SIP/2.0 200 OK\r\n");
+    for line in request.lines() {
+        if ["Via:", "From:", "To:", "Call-ID:", "CSeq:"].iter().any(|name| line.starts_with(name)) {
+            response.push_str(line);
+            response.push_str("\r\n");
+        }
+    }
+    response.push_str("Content-Length: 0\r\n\r\n");
+    signaling.send_to(response.as_bytes(), gateway_addr).await.unwrap();
+
+    let mut received = Vec::new();
+    while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+        let len = timeout(Duration::from_secs(5), stream.read(&mut buf)).await.unwrap().unwrap();
+        assert!(len > 0, "gateway TLS bağlantısını yanıt göndermeden kapattı");
+        received.extend_from_slice(&buf[..len]);
+    }
+    let received = String::from_utf8(received).unwrap();
+    assert!(received.starts_with("SIP/2.0 200 OK\r\n"), "alınan yanıt: {received}");
+    let via = received.lines().find(|line| line.starts_with("Via:")).unwrap();
+    assert!(via.starts_with(&format!("Via: SIP/2.0/TLS {client_addr};branch={BRANCH}")), "en üstteki Via: {via}");
+    assert_eq!(received.lines().filter(|line| line.starts_with("Via:")).count(), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
\ No newline at end of file